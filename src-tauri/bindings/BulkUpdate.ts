@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CustomFieldUpdate } from "./CustomFieldUpdate";
+import type { Status } from "./Status";
+
+export interface BulkUpdate { add_tags: Array<string>, remove_tags: Array<string>, rating: number | null, favorite: boolean | null, status: Status | null, custom_fields: Array<CustomFieldUpdate>, }
\ No newline at end of file
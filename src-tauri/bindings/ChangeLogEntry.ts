@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChangeOperation } from "./ChangeOperation";
+
+export interface ChangeLogEntry { id: number, batch_id: number, hash: string, operation: ChangeOperation, before: string | null, after: string | null, timestamp: number, undone: boolean, }
\ No newline at end of file
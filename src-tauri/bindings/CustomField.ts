@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FieldType } from "./FieldType";
+
+export interface CustomField { id: number, name: string, field_type: FieldType, options: Array<string> | null, }
\ No newline at end of file
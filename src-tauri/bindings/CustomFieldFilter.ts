@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FilterOperator } from "./FilterOperator";
+
+export interface CustomFieldFilter { field_id: number, operator: FilterOperator, value: string, }
\ No newline at end of file
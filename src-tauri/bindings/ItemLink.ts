@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ItemLink { id: number, source_hash: string, target_hash: string, relation: string, timestamp_created: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinkDirection } from "./LinkDirection";
+
+export interface LinkedItem { link_id: number | null, relation: string, direction: LinkDirection, hash: string, name: string, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Status } from "./Status";
+
+export interface MetadataPatch { name?: string, tags?: Array<string> | null, notes?: string | null, rating?: number, favorite?: boolean, status?: Status, }
\ No newline at end of file
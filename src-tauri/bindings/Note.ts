@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface Note { id: number, hash: string, body: string, timestamp_created: number, timestamp_modified: number, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface Preferences { recent_searches: Array<string>, show_file_extensions: boolean, }
\ No newline at end of file
+export interface Preferences { recent_searches: Array<string>, show_file_extensions: boolean, thumbnail_cache_limit_mb: number, trash_retention_days: number, }
\ No newline at end of file
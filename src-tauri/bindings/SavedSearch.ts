@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchFilter } from "./SearchFilter";
+
+export interface SavedSearch { id: number, name: string, query: string, filter: SearchFilter, item_count: number, timestamp_created: number, }
\ No newline at end of file
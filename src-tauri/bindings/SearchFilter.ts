@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CustomFieldFilter } from "./CustomFieldFilter";
+import type { SortBy } from "./SortBy";
+
+export interface SearchFilter { custom_fields: Array<CustomFieldFilter>, min_rating: number | null, favorites_only: boolean, sort_by: SortBy, collection_id: number | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RuleCondition } from "./RuleCondition";
+
+export interface TagRule { id: number, name: string, condition: RuleCondition, pattern: string, tags: Array<string> | null, enabled: boolean, }
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE "content_text"
\ No newline at end of file
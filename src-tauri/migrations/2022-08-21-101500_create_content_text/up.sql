@@ -0,0 +1,5 @@
+CREATE TABLE "content_text" (
+    "hash"	TEXT NOT NULL UNIQUE,
+    "body"	TEXT NOT NULL,
+    PRIMARY KEY("hash")
+);
\ No newline at end of file
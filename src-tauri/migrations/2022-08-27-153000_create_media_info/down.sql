@@ -0,0 +1 @@
+DROP TABLE "media_info"
\ No newline at end of file
@@ -0,0 +1,6 @@
+CREATE TABLE "media_info" (
+    "hash"	TEXT NOT NULL,
+    "key"	TEXT NOT NULL,
+    "value"	TEXT NOT NULL,
+    PRIMARY KEY("hash", "key")
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP TABLE "custom_field_value";
+DROP TABLE "custom_field"
\ No newline at end of file
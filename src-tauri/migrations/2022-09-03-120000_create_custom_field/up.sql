@@ -0,0 +1,14 @@
+CREATE TABLE "custom_field" (
+    "id"	INTEGER NOT NULL,
+    "name"	TEXT NOT NULL UNIQUE,
+    "fieldType"	TEXT NOT NULL,
+    "options"	TEXT,
+    PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE TABLE "custom_field_value" (
+    "hash"	TEXT NOT NULL,
+    "fieldId"	INTEGER NOT NULL,
+    "value"	TEXT NOT NULL,
+    PRIMARY KEY("hash", "fieldId")
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE "metadata" DROP COLUMN "favorite";
+ALTER TABLE "metadata" DROP COLUMN "rating"
\ No newline at end of file
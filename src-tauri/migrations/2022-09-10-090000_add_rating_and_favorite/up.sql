@@ -0,0 +1,2 @@
+ALTER TABLE "metadata" ADD COLUMN "rating" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "metadata" ADD COLUMN "favorite" BOOLEAN NOT NULL DEFAULT 0;
\ No newline at end of file
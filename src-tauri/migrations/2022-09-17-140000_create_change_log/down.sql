@@ -0,0 +1 @@
+DROP TABLE "change_log"
\ No newline at end of file
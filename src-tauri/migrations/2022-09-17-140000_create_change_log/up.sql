@@ -0,0 +1,14 @@
+CREATE TABLE "change_log" (
+    "id"	INTEGER NOT NULL,
+    "batchId"	INTEGER NOT NULL,
+    "hash"	TEXT NOT NULL,
+    "operation"	TEXT NOT NULL,
+    "before"	TEXT,
+    "after"	TEXT,
+    "timestamp"	INTEGER NOT NULL,
+    "undone"	BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "change_log_hash" ON "change_log" ("hash");
+CREATE INDEX "change_log_batch" ON "change_log" ("batchId");
\ No newline at end of file
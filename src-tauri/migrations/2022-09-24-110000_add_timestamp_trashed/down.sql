@@ -0,0 +1 @@
+ALTER TABLE "metadata" DROP COLUMN "timestampTrashed"
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE "metadata" ADD COLUMN "timestampTrashed" INTEGER;
\ No newline at end of file
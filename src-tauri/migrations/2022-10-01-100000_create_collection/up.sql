@@ -0,0 +1,14 @@
+CREATE TABLE "collection" (
+    "id"	INTEGER NOT NULL,
+    "name"	TEXT NOT NULL,
+    "description"	TEXT,
+    "timestampCreated"	INTEGER NOT NULL,
+    PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE TABLE "collection_item" (
+    "collectionId"	INTEGER NOT NULL,
+    "hash"	TEXT NOT NULL,
+    "position"	INTEGER NOT NULL,
+    PRIMARY KEY("collectionId", "hash")
+);
\ No newline at end of file
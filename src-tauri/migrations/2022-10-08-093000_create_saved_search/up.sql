@@ -0,0 +1,9 @@
+CREATE TABLE "saved_search" (
+    "id"	INTEGER NOT NULL,
+    "name"	TEXT NOT NULL UNIQUE,
+    "query"	TEXT NOT NULL,
+    "filter"	TEXT NOT NULL,
+    "itemCount"	INTEGER NOT NULL DEFAULT 0,
+    "timestampCreated"	INTEGER NOT NULL,
+    PRIMARY KEY("id" AUTOINCREMENT)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE "tag_rule"
\ No newline at end of file
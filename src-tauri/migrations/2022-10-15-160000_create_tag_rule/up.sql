@@ -0,0 +1,9 @@
+CREATE TABLE "tag_rule" (
+    "id"	INTEGER NOT NULL,
+    "name"	TEXT NOT NULL,
+    "condition"	TEXT NOT NULL,
+    "pattern"	TEXT NOT NULL,
+    "tags"	TEXT,
+    "enabled"	BOOLEAN NOT NULL DEFAULT 1,
+    PRIMARY KEY("id" AUTOINCREMENT)
+);
\ No newline at end of file
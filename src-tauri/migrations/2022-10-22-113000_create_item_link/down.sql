@@ -0,0 +1 @@
+DROP TABLE "item_link"
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE "item_link" (
+    "id"	INTEGER NOT NULL,
+    "sourceHash"	TEXT NOT NULL,
+    "targetHash"	TEXT NOT NULL,
+    "relation"	TEXT NOT NULL,
+    "timestampCreated"	INTEGER NOT NULL,
+    PRIMARY KEY("id" AUTOINCREMENT),
+    UNIQUE("sourceHash", "targetHash", "relation")
+);
+
+CREATE INDEX "item_link_sourceHash" ON "item_link" ("sourceHash");
+CREATE INDEX "item_link_targetHash" ON "item_link" ("targetHash");
\ No newline at end of file
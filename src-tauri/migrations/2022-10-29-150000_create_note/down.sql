@@ -0,0 +1 @@
+DROP TABLE "note"
\ No newline at end of file
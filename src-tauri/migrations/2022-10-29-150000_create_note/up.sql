@@ -0,0 +1,10 @@
+CREATE TABLE "note" (
+    "id"	INTEGER NOT NULL,
+    "hash"	TEXT NOT NULL,
+    "body"	TEXT NOT NULL,
+    "timestampCreated"	INTEGER NOT NULL,
+    "timestampModified"	INTEGER NOT NULL,
+    PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "note_hash" ON "note" ("hash");
\ No newline at end of file
@@ -0,0 +1,120 @@
+use crate::{
+    foc_error::{FocError, Result},
+    models::metadata::{self, ContentType},
+};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Read, path::Path};
+use ts_rs::TS;
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct ArchiveEntry {
+    pub path: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    fn from_path(archive_path: &str) -> Option<Self> {
+        let lowercase_path = archive_path.to_lowercase();
+
+        if lowercase_path.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if lowercase_path.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if lowercase_path.ends_with(".tar.gz") || lowercase_path.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads the table of contents of an archive without extracting anything to disk,
+/// returns `None` for archive formats we can't look into
+pub fn list_archive_entries(archive_path: &str) -> Result<Option<Vec<ArchiveEntry>>> {
+    let format = match ArchiveFormat::from_path(archive_path) {
+        Some(format) => format,
+        None => return Ok(None),
+    };
+
+    let file = File::open(archive_path)?;
+    let entries = match format {
+        ArchiveFormat::Zip => list_zip_entries(file)?,
+        ArchiveFormat::Tar => list_tar_entries(file)?,
+        ArchiveFormat::TarGz => list_tar_entries(GzDecoder::new(file))?,
+    };
+
+    Ok(Some(entries))
+}
+
+fn list_zip_entries(file: File) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entries = Vec::with_capacity(archive.len());
+
+    for index in 0..archive.len() {
+        // Raw access skips decompression since we only care about the headers
+        let zip_file = archive.by_index_raw(index)?;
+        entries.push(ArchiveEntry {
+            path: zip_file.name().to_owned(),
+            size: zip_file.size(),
+            is_dir: zip_file.is_dir(),
+        });
+    }
+
+    Ok(entries)
+}
+
+fn list_tar_entries<R: Read>(reader: R) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        entries.push(ArchiveEntry {
+            path: entry.path()?.to_string_lossy().into_owned(),
+            size: entry.size(),
+            is_dir: entry.header().entry_type().is_dir(),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Builds the searchable text for an archive, which is just the names of the files inside it
+pub fn archive_entries_to_text(entries: &[ArchiveEntry]) -> Option<String> {
+    let text: Vec<&str> = entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .filter_map(|entry| Path::new(&entry.path).file_name()?.to_str())
+        .collect();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.join("\n"))
+    }
+}
+
+#[tauri::command]
+pub async fn get_archive_entries(hash: String) -> Result<Vec<ArchiveEntry>> {
+    let metadata = metadata::get_metadata_by_hash(&hash).await?;
+
+    if !matches!(metadata.content_type, ContentType::Archive) {
+        return Err(FocError::Metadata(format!(
+            "{} is not an archive",
+            metadata.name
+        )));
+    }
+
+    list_archive_entries(&metadata.path)?
+        .ok_or_else(|| FocError::Metadata("Unsupported archive format".to_owned()))
+}
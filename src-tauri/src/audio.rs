@@ -0,0 +1,192 @@
+use crate::foc_error::Result;
+use image::{Rgba, RgbaImage};
+use std::{fs::File, io::ErrorKind};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::DecoderOptions,
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey, Visual},
+    probe::{Hint, ProbeResult},
+};
+
+const WAVEFORM_WIDTH: u32 = 512;
+const WAVEFORM_HEIGHT: u32 = 256;
+// Amount of samples that get reduced into one peak before scaling to the final width
+const SAMPLES_PER_PEAK: usize = 1024;
+
+pub fn probe_audio(audio_path: &str, extension: Option<&str>) -> Result<ProbeResult> {
+    let file = File::open(audio_path)?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        source,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    Ok(probed)
+}
+
+/// Returns the embedded cover art, preferring the front cover if there are multiple pictures
+pub fn extract_cover_art(audio_path: &str, extension: Option<&str>) -> Result<Option<Vec<u8>>> {
+    let mut probed = probe_audio(audio_path, extension)?;
+    let mut visuals: Vec<Visual> = Vec::new();
+
+    // ID3 tags are read while probing, everything else lives inside the container
+    if let Some(mut metadata) = probed.metadata.get() {
+        visuals.extend(latest_visuals(metadata.skip_to_latest()));
+    }
+    visuals.extend(latest_visuals(probed.format.metadata().skip_to_latest()));
+
+    let cover = visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())
+        .map(|visual| visual.data.to_vec());
+
+    Ok(cover)
+}
+
+fn latest_visuals(revision: Option<&MetadataRevision>) -> Vec<Visual> {
+    revision
+        .map(|revision| revision.visuals().to_vec())
+        .unwrap_or_default()
+}
+
+/// Reads the common tags like artist and album together with the codec of the default track
+pub fn read_audio_tags(audio_path: &str, extension: Option<&str>) -> Result<Vec<(String, String)>> {
+    let mut probed = probe_audio(audio_path, extension)?;
+    let mut tags = Vec::new();
+
+    if let Some(mut metadata) = probed.metadata.get() {
+        if let Some(revision) = metadata.skip_to_latest() {
+            collect_tags(revision, &mut tags);
+        }
+    }
+    if let Some(revision) = probed.format.metadata().skip_to_latest() {
+        collect_tags(revision, &mut tags);
+    }
+
+    let codec = probed
+        .format
+        .default_track()
+        .and_then(|track| symphonia::default::get_codecs().get_codec(track.codec_params.codec));
+    if let Some(codec) = codec {
+        tags.push(("audio_codec".to_owned(), codec.short_name.to_owned()));
+    }
+
+    Ok(tags)
+}
+
+fn collect_tags(revision: &MetadataRevision, tags: &mut Vec<(String, String)>) {
+    for tag in revision.tags() {
+        let key = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => "title",
+            Some(StandardTagKey::Artist) => "artist",
+            Some(StandardTagKey::AlbumArtist) => "album_artist",
+            Some(StandardTagKey::Album) => "album",
+            Some(StandardTagKey::Composer) => "composer",
+            Some(StandardTagKey::Genre) => "genre",
+            Some(StandardTagKey::Date) => "date",
+            _ => continue,
+        };
+
+        let value = tag.value.to_string();
+        if !value.trim().is_empty() {
+            tags.push((key.to_owned(), value));
+        }
+    }
+}
+
+/// Decodes the whole default track and draws its peaks, mirrored around the center line
+pub fn render_waveform(audio_path: &str, extension: Option<&str>) -> Result<Option<RgbaImage>> {
+    let mut probed = probe_audio(audio_path, extension)?;
+
+    let track = match probed.format.default_track() {
+        Some(track) => track,
+        None => return Ok(None),
+    };
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut peaks: Vec<f32> = Vec::new();
+    let mut current_peak = 0.0_f32;
+    let mut samples_in_peak = 0;
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match probed.format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(error) => return Err(error.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame here and there doesn't ruin the waveform
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        let buf = sample_buf.get_or_insert_with(|| {
+            SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec())
+        });
+        buf.copy_interleaved_ref(decoded);
+
+        for sample in buf.samples() {
+            current_peak = current_peak.max(sample.abs());
+            samples_in_peak += 1;
+            if samples_in_peak == SAMPLES_PER_PEAK {
+                peaks.push(current_peak);
+                current_peak = 0.0;
+                samples_in_peak = 0;
+            }
+        }
+    }
+
+    if samples_in_peak > 0 {
+        peaks.push(current_peak);
+    }
+    if peaks.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(draw_waveform(&peaks)))
+}
+
+fn draw_waveform(peaks: &[f32]) -> RgbaImage {
+    let background = Rgba([30, 30, 36, 255]);
+    let foreground = Rgba([120, 160, 255, 255]);
+    let mut image = RgbaImage::from_pixel(WAVEFORM_WIDTH, WAVEFORM_HEIGHT, background);
+    let center = WAVEFORM_HEIGHT / 2;
+
+    for x in 0..WAVEFORM_WIDTH {
+        let start = x as usize * peaks.len() / WAVEFORM_WIDTH as usize;
+        let end = ((x as usize + 1) * peaks.len() / WAVEFORM_WIDTH as usize).max(start + 1);
+        let peak = peaks[start..end.min(peaks.len())]
+            .iter()
+            .fold(0.0_f32, |max, peak| max.max(*peak))
+            .min(1.0);
+
+        let half_height = ((peak * center as f32) as u32).max(1);
+        for y in center.saturating_sub(half_height)..(center + half_height).min(WAVEFORM_HEIGHT) {
+            image.put_pixel(x, y, foreground);
+        }
+    }
+
+    image
+}
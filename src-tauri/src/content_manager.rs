@@ -1,28 +1,47 @@
 use crate::{
+    archive::{archive_entries_to_text, list_archive_entries},
+    database::DATABASE_INSTANCE,
     directory::{create_base_dirs, extension_from_path, get_dir_path, Dir},
-    file_utils::{get_duration, get_image_dimensions, get_video_dimensions},
+    favicon,
+    file_utils::{
+        extract_media_info, extract_text, get_duration, get_image_dimensions, get_video_dimensions,
+    },
     foc_error::{FocError, Result},
     models::{
-        metadata::{self, ContentType, Metadata},
+        change_log::{self, ChangeOperation},
+        collection,
+        content_text::{self, ContentText},
+        custom_field, item_link,
+        media_info::{self, MediaInfo},
+        metadata::{self, ContentType, Metadata, Status},
+        note,
         smart_folder::validate_folder_path,
+        tag_rule::{self, TagRule},
     },
+    preferences,
+    schema::{content_text as content_text_schema, metadata as metadata_schema},
     searcher,
+    thumbnail::{self, create_thumbnail, generate_thumbnail_from_file},
     web_extension::Request,
 };
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Cursor, Seek},
+    io::{Cursor, Read},
     path::Path,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use diesel::prelude::*;
 use memmap2::MmapOptions;
 use mime::Mime;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use thumbnailer::{create_thumbnails, ThumbnailSize};
+use ts_rs::TS;
 
 pub async fn check_file_integrity() -> Result<()> {
     create_base_dirs()?;
+    purge_expired_trash().await?;
+    thumbnail::clean_thumbnail_cache().await?;
 
     /* let all_metadata = database::get_all_metadata().await?; */
 
@@ -73,105 +92,532 @@ pub async fn check_file_integrity() -> Result<()> {
     // check system last modified time
 } */
 
+/// Stores everything we know about a file in the database and creates its thumbnails,
+/// indexing the returned metadata is up to the caller
+async fn store_file(file_path: String, tag_rules: &[TagRule]) -> Result<Metadata> {
+    let file_kind_option = infer::get_from_path(&file_path)?;
+
+    // TODO: make this extension into lowecase so its case insensitive (maybe this is automatic? test it)
+    let extension = match &file_kind_option {
+        Some(file_kind) => Some(file_kind.extension().to_string()),
+        None => extension_from_path(&file_path),
+    };
+    let content_type = ContentType::detect(file_kind_option.as_ref(), extension.as_deref());
+
+    let file = File::open(&file_path)?;
+    let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+    let file_hash: String = Sha256::digest(&mmap)
+        .to_vec()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    let file_name_no_ext = Path::new(&file_path)
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+
+    let width_and_height: Option<(i32, i32)> = match content_type {
+        ContentType::Image | ContentType::Gif => get_image_dimensions(&file_path),
+        ContentType::Video => get_video_dimensions(&file_path),
+        _ => None,
+    };
+
+    let duration = match content_type {
+        ContentType::Audio | ContentType::Video => get_duration(&file_path),
+        _ => None,
+    };
+
+    let timestamp_created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    let mut metadata = Metadata {
+        hash: file_hash,
+        name: file_name_no_ext,
+        path: file_path,
+        content_type,
+        status: metadata::Status::Valid,
+        timestamp_created,
+        timestamp_modified: timestamp_created,
+        extension,
+        tags: None,
+        notes: None,
+        width: width_and_height.map(|(width, _height)| width),
+        height: width_and_height.map(|(_width, height)| height),
+        duration,
+        rating: 0,
+        favorite: false,
+        timestamp_trashed: None,
+    };
+    tag_rule::apply_tag_rules(tag_rules, &mut metadata);
+
+    let metadata_for_database = metadata.clone();
+    metadata::insert_metadata(metadata_for_database).await?;
+
+    let body = match metadata.content_type {
+        ContentType::Document | ContentType::Text | ContentType::Code => {
+            extract_text(&metadata.path, metadata.extension.as_deref())
+        }
+        ContentType::Archive => match list_archive_entries(&metadata.path) {
+            Ok(entries) => entries.and_then(|entries| archive_entries_to_text(&entries)),
+            Err(error) => {
+                println!("Couldn't read contents of {}: {}", metadata.path, error);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    if let Some(body) = body {
+        content_text::insert_content_text(ContentText {
+            hash: metadata.hash.clone(),
+            body,
+        })
+        .await?;
+    }
+
+    let media_info: Vec<MediaInfo> = extract_media_info(
+        &metadata.path,
+        &metadata.content_type,
+        metadata.extension.as_deref(),
+    )
+    .into_iter()
+    .map(|(key, value)| MediaInfo {
+        hash: metadata.hash.clone(),
+        key,
+        value,
+    })
+    .collect();
+    if !media_info.is_empty() {
+        media_info::insert_media_info(media_info).await?;
+    }
+
+    generate_thumbnail_from_file(&metadata, &file)?;
+
+    Ok(metadata)
+}
+
 #[tauri::command]
 pub async fn add_files(file_paths: Vec<String>) -> Result<()> {
+    let tag_rules = tag_rule::get_enabled_tag_rules()?;
     let mut metadata_to_index: Vec<Metadata> = Vec::with_capacity(file_paths.len());
     // TODO: this can definitely be multithreaded with rayon, also check other loops
     for file_path in file_paths {
-        let content_type: ContentType;
-        let extension: Option<String>;
+        metadata_to_index.push(store_file(file_path, &tag_rules).await?);
+    }
 
-        let file_kind_option = infer::get_from_path(&file_path)?;
+    searcher::index_metadata(&metadata_to_index)?;
 
-        if let Some(file_kind) = file_kind_option {
-            // TODO: make this extension into lowecase so its case insensitive (maybe this is automatic? test it)
-            content_type = ContentType::from_kind(&file_kind)?;
-            extension = Some(file_kind.extension().to_string());
-        } else {
-            content_type = ContentType::Other;
-            extension = extension_from_path(&file_path);
-        }
+    Ok(())
+}
+
+/// Moves items to the trash, where they can be restored until they're purged.
+/// With `delete_files` the files themselves go to the system trash, which undo can't bring back.
+#[tauri::command]
+pub async fn delete_content(hashes: Vec<String>, delete_files: Option<bool>) -> Result<()> {
+    let timestamp_trashed = current_timestamp();
 
-        let file = File::open(&file_path)?;
-        let mmap = unsafe { MmapOptions::new().map(&file)? };
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let trashed_metadata = conn.transaction::<_, FocError, _>(|conn| {
+        let before = change_log::take_snapshots(conn, &hashes, false)?;
+        diesel::update(metadata_schema::table.filter(metadata_schema::hash.eq_any(&hashes)))
+            .set((
+                metadata_schema::status.eq(Status::Trashed.to_string()),
+                metadata_schema::timestampTrashed.eq(Some(timestamp_trashed)),
+            ))
+            .execute(conn)?;
 
-        let file_hash: String = Sha256::digest(&mmap)
-            .to_vec()
+        let after = change_log::take_snapshots(conn, &hashes, false)?;
+        let trashed_metadata: Vec<Metadata> = after
             .iter()
-            .map(ToString::to_string)
+            .map(|snapshot| snapshot.metadata.clone())
             .collect();
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)?;
 
-        let file_name_no_ext = Path::new(&file_path)
-            .file_stem()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned();
+        Ok(trashed_metadata)
+    })?;
 
-        let width_and_height: Option<(i32, i32)> = match content_type {
-            ContentType::Image => get_image_dimensions(&file_path),
-            ContentType::Video => get_video_dimensions(&file_path),
-            _ => None,
-        };
+    searcher::delete_metadata(&hashes)?;
 
-        let duration = match content_type {
-            ContentType::Audio => get_duration(&file_path),
-            _ => None,
-        };
+    if delete_files.unwrap_or(false) {
+        for metadata in trashed_metadata.iter() {
+            if !metadata.content_type.has_file() {
+                continue;
+            }
+            if Path::new(&metadata.path).exists() {
+                trash::delete(&metadata.path)?;
+            }
+        }
+    }
 
-        let timestamp_created = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs() as i32;
-
-        let metadata = Metadata {
-            hash: file_hash,
-            name: file_name_no_ext,
-            path: file_path,
-            content_type,
-            status: metadata::Status::Valid,
-            timestamp_created,
-            timestamp_modified: timestamp_created,
-            extension,
-            tags: None,
-            notes: None,
-            width: width_and_height.map(|(width, _height)| width),
-            height: width_and_height.map(|(_width, height)| height),
-            duration,
-        };
+    Ok(())
+}
 
-        let metadata_for_database = metadata.clone();
-        metadata::insert_metadata(metadata_for_database).await?;
+#[tauri::command]
+pub async fn restore_content(hashes: Vec<String>) -> Result<Vec<Metadata>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let restored_metadata = conn.transaction::<_, FocError, _>(|conn| {
+        let before = change_log::take_snapshots(conn, &hashes, false)?;
+        diesel::update(
+            metadata_schema::table
+                .filter(metadata_schema::hash.eq_any(&hashes))
+                .filter(metadata_schema::status.eq(Status::Trashed.to_string())),
+        )
+        .set((
+            metadata_schema::status.eq(Status::Valid.to_string()),
+            metadata_schema::timestampTrashed.eq(None::<i32>),
+        ))
+        .execute(conn)?;
 
-        generate_thumbnail_from_file(&metadata, &file)?;
-        metadata_to_index.push(metadata);
-    }
+        let after = change_log::take_snapshots(conn, &hashes, false)?;
+        let restored_metadata: Vec<Metadata> = after
+            .iter()
+            .map(|snapshot| snapshot.metadata.clone())
+            .collect();
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)?;
 
-    searcher::index_metadata(&metadata_to_index)?;
+        Ok(restored_metadata)
+    })?;
+
+    searcher::update_metadata(&restored_metadata)?;
+
+    Ok(restored_metadata)
+}
+
+async fn remove_items(hashes: &Vec<String>) -> Result<()> {
+    metadata::delete_metadata(hashes).await?;
+    content_text::delete_content_text(hashes).await?;
+    media_info::delete_media_info(hashes).await?;
+    custom_field::delete_custom_field_values(hashes).await?;
+    collection::remove_from_all_collections(hashes).await?;
+    item_link::delete_item_links(hashes).await?;
+    note::delete_notes(hashes).await?;
+    searcher::delete_metadata(hashes)?;
 
     Ok(())
 }
 
+/// Removes items for good, along with everything stored about them. Their thumbnails and
+/// favicons stay until the history is dropped, so that undo can still bring everything back.
 #[tauri::command]
-pub async fn delete_content(hashes: Vec<String>) -> Result<()> {
-    metadata::delete_metadata(&hashes).await?;
-    searcher::delete_metadata(&hashes)?;
+pub async fn purge_content(hashes: Vec<String>) -> Result<()> {
+    let before = change_log::snapshot_items(&hashes, true)?;
 
-    // TODO: deletion could actually delete files on disk as an option
-    /* fs::remove_file(hashes.path)?; */
+    remove_items(&hashes).await?;
+    change_log::record_item_changes(ChangeOperation::Delete, before, Vec::new())?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn update_content(metadata: Metadata) -> Result<()> {
+pub async fn get_trashed_content() -> Result<Vec<Metadata>> {
+    metadata::get_trashed_metadata().await
+}
+
+#[tauri::command]
+pub async fn empty_trash() -> Result<()> {
+    let hashes = metadata::get_trashed_metadata()
+        .await?
+        .into_iter()
+        .map(|metadata| metadata.hash)
+        .collect();
+
+    purge_content(hashes).await
+}
+
+/// Purges whatever has been in the trash for longer than the retention from the preferences
+pub async fn purge_expired_trash() -> Result<()> {
+    let retention_days = preferences::get_preferences()?.trash_retention_days;
+    if retention_days == 0 {
+        return Ok(());
+    }
+
+    let cutoff = current_timestamp() - (retention_days as i32).saturating_mul(24 * 60 * 60);
+    let expired_hashes: Vec<String> = metadata::get_trashed_metadata()
+        .await?
+        .into_iter()
+        .filter(|metadata| {
+            metadata
+                .timestamp_trashed
+                .map_or(false, |trashed| trashed < cutoff)
+        })
+        .map(|metadata| metadata.hash)
+        .collect();
+
+    if expired_hashes.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "Purging {} expired items from the trash",
+        expired_hashes.len()
+    );
+    // Nobody asked for this, so it can't be undone either and the items' history goes with them
+    remove_items(&expired_hashes).await?;
+    thumbnail::delete_thumbnails(&expired_hashes)?;
+    favicon::delete_favicons(&expired_hashes)?;
+    change_log::delete_item_history(&expired_hashes)
+}
+
+#[tauri::command]
+pub async fn update_content(mut metadata: Metadata) -> Result<()> {
     println!("Updating {:?}", metadata.name);
-    searcher::update_metadata(&metadata)?;
+    let hashes = vec![metadata.hash.clone()];
+    let before = change_log::snapshot_items(&hashes, false)?;
+    metadata.timestamp_modified = current_timestamp();
+    if let Some(stored) = before.first() {
+        // Two edits within the same second still need different timestamps to be told apart
+        metadata.timestamp_modified = metadata
+            .timestamp_modified
+            .max(stored.metadata.timestamp_modified + 1);
+        // Ratings and favorites have their own commands, a payload without them mustn't reset them
+        metadata.rating = stored.metadata.rating;
+        metadata.favorite = stored.metadata.favorite;
+        // The trash timestamp follows the stored status, whatever the caller sent along
+        let status = metadata.status.clone();
+        metadata.status = stored.metadata.status.clone();
+        metadata.timestamp_trashed = stored.metadata.timestamp_trashed;
+        let timestamp = metadata.timestamp_modified;
+        set_status(&mut metadata, status, timestamp);
+    }
+
+    searcher::update_metadata(std::slice::from_ref(&metadata))?;
     metadata::update_metadata(metadata).await?;
 
+    let after = change_log::snapshot_items(&hashes, false)?;
+    change_log::record_item_changes(ChangeOperation::Update, before, after)
+}
+
+#[tauri::command]
+pub async fn set_rating(hashes: Vec<String>, rating: i32) -> Result<()> {
+    validate_rating(rating)?;
+    let before = change_log::snapshot_items(&hashes, false)?;
+    metadata::update_rating(&hashes, rating).await?;
+
+    let after = change_log::snapshot_items(&hashes, false)?;
+    change_log::record_item_changes(ChangeOperation::Update, before, after)?;
+    reindex_content(&hashes).await
+}
+
+#[tauri::command]
+pub async fn set_favorite(hashes: Vec<String>, favorite: bool) -> Result<()> {
+    let before = change_log::snapshot_items(&hashes, false)?;
+    metadata::update_favorite(&hashes, favorite).await?;
+
+    let after = change_log::snapshot_items(&hashes, false)?;
+    change_log::record_item_changes(ChangeOperation::Update, before, after)?;
+    reindex_content(&hashes).await
+}
+
+fn validate_rating(rating: i32) -> Result<()> {
+    if (0..=metadata::MAX_RATING).contains(&rating) {
+        Ok(())
+    } else {
+        Err(FocError::Metadata(format!(
+            "Rating must be between 0 and {}",
+            metadata::MAX_RATING
+        )))
+    }
+}
+
+async fn reindex_content(hashes: &[String]) -> Result<()> {
+    let metadata = metadata::get_metadata_by_hashes(hashes).await?;
+    searcher::update_metadata(&metadata)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct CustomFieldUpdate {
+    pub field_id: i32,
+    /// `None` clears the field
+    pub value: Option<String>,
+}
+
+/// Changes applied to every selected item, anything left empty stays as it is
+#[derive(Serialize, Deserialize, Default, Debug, Clone, TS)]
+#[ts(export)]
+pub struct BulkUpdate {
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub remove_tags: Vec<String>,
+    pub rating: Option<i32>,
+    pub favorite: Option<bool>,
+    pub status: Option<Status>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldUpdate>,
+}
+
+fn apply_tag_changes(
+    tags: Option<Vec<String>>,
+    add: &[String],
+    remove: &[String],
+) -> Option<Vec<String>> {
+    let mut tags = tags.unwrap_or_default();
+    tags.retain(|tag| !remove.contains(tag));
+    for tag in add {
+        let tag = tag.trim();
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+
+    if tags.is_empty() {
+        None
+    } else {
+        Some(tags)
+    }
+}
+
+/// Changes the status the way `delete_content` and `restore_content` do,
+/// so everything in the trash knows when it got there and can expire
+fn set_status(metadata: &mut Metadata, status: Status, timestamp: i32) {
+    let was_trashed = matches!(metadata.status, Status::Trashed);
+    let is_trashed = matches!(status, Status::Trashed);
+    if is_trashed && !was_trashed {
+        metadata.timestamp_trashed = Some(timestamp);
+    } else if !is_trashed {
+        metadata.timestamp_trashed = None;
+    }
+    metadata.status = status;
+}
+
+/// Updates many items in one database transaction and a single index commit,
+/// either every item gets the changes or none of them do
+#[tauri::command]
+pub async fn bulk_update_content(hashes: Vec<String>, update: BulkUpdate) -> Result<Vec<Metadata>> {
+    if let Some(rating) = update.rating {
+        validate_rating(rating)?;
+    }
+
+    let timestamp_modified = current_timestamp();
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let updated_metadata = conn.transaction::<_, FocError, _>(|conn| {
+        let before = change_log::take_snapshots(conn, &hashes, false)?;
+        let mut all_metadata = metadata_schema::table
+            .filter(metadata_schema::hash.eq_any(&hashes))
+            .load::<Metadata>(conn)?;
+
+        for metadata in all_metadata.iter_mut() {
+            metadata.tags =
+                apply_tag_changes(metadata.tags.take(), &update.add_tags, &update.remove_tags);
+            if let Some(rating) = update.rating {
+                metadata.rating = rating;
+            }
+            if let Some(favorite) = update.favorite {
+                metadata.favorite = favorite;
+            }
+            if let Some(status) = &update.status {
+                set_status(metadata, status.clone(), timestamp_modified);
+            }
+            metadata.timestamp_modified = timestamp_modified.max(metadata.timestamp_modified + 1);
+
+            diesel::update(&*metadata)
+                .set(metadata.clone())
+                .execute(conn)?;
+        }
+
+        for custom_field_update in update.custom_fields.iter() {
+            custom_field::set_custom_field_values(
+                conn,
+                &hashes,
+                custom_field_update.field_id,
+                custom_field_update.value.as_deref(),
+            )?;
+        }
+
+        let after = change_log::take_snapshots(conn, &hashes, false)?;
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)?;
+
+        Ok(all_metadata)
+    })?;
+
+    searcher::update_metadata(&updated_metadata)?;
+
+    Ok(updated_metadata)
+}
+
+/// Splits a base64 data URL like `data:image/png;base64,...` into its type and bytes
+fn decode_data_url(data_url: &str) -> Result<(Mime, Vec<u8>)> {
+    let (header, data) = data_url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(|| FocError::Extension("Invalid data URL".to_owned()))?;
+    let mime_type = header
+        .strip_suffix(";base64")
+        .ok_or_else(|| FocError::Extension("Data URL isn't base64 encoded".to_owned()))?
+        .parse::<Mime>()
+        .map_err(|_| FocError::Extension(format!("Invalid data URL type: {}", header)))?;
+
+    Ok((mime_type, base64::decode(data)?))
+}
+
+/// Tags and notes the user picked in the extension popup
+fn apply_popup_input(request: &Request, metadata: &mut Metadata) {
+    if let Some(tags) = &request.tags {
+        metadata.tags = apply_tag_changes(metadata.tags.take(), tags, &[]);
+    }
+    if let Some(notes) = request
+        .notes
+        .as_deref()
+        .map(str::trim)
+        .filter(|notes| !notes.is_empty())
+    {
+        // Saving something again adds to the notes it already has
+        metadata.notes = Some(match metadata.notes.take() {
+            Some(existing) if !existing.trim().is_empty() => format!("{}\n\n{}", existing, notes),
+            _ => notes.to_owned(),
+        });
+    }
+}
+
+/// Saving something that's already in the library brings it back from the trash and adds
+/// what was entered in the popup, as one undoable change instead of a second item
+async fn update_saved_again(request: &Request, mut metadata: Metadata) -> Result<Metadata> {
+    let hashes = vec![metadata.hash.clone()];
+    let before = change_log::snapshot_items(&hashes, false)?;
+
+    let timestamp_modified = current_timestamp().max(metadata.timestamp_modified + 1);
+    set_status(&mut metadata, Status::Valid, timestamp_modified);
+    apply_popup_input(request, &mut metadata);
+    metadata.timestamp_modified = timestamp_modified;
+    metadata::update_metadata(metadata.clone()).await?;
+
+    let after = change_log::snapshot_items(&hashes, false)?;
+    change_log::record_item_changes(ChangeOperation::Update, before, after)?;
+
+    Ok(metadata)
+}
+
+/// Keeps what the extension told us about the page as media info, so it's indexed too
+async fn store_page_info<const N: usize>(
+    hash: &str,
+    page_info: [(&str, Option<&str>); N],
+) -> Result<()> {
+    let media_info: Vec<MediaInfo> = page_info
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = value?.trim();
+            (!value.is_empty()).then(|| MediaInfo {
+                hash: hash.to_owned(),
+                key: key.to_owned(),
+                value: value.to_owned(),
+            })
+        })
+        .collect();
+    if !media_info.is_empty() {
+        media_info::insert_media_info(media_info).await?;
+    }
+
     Ok(())
 }
 
-pub async fn add_url_from_extension(request: &Request<'_>) -> Result<()> {
+pub async fn add_url_from_extension(request: &Request) -> Result<()> {
     // TODO: we gotta make sure it's a proper website (perhaps theres some check for this) and not edge://
 
     let url_hash: String = Sha256::digest(&request.url)
@@ -180,85 +626,468 @@ pub async fn add_url_from_extension(request: &Request<'_>) -> Result<()> {
         .map(ToString::to_string)
         .collect();
 
-    let base64 = request
-        .image
-        .strip_prefix("data:image/png;base64,")
-        .ok_or_else(|| FocError::Metadata("Couldn't strip prefix!".to_owned()))?;
+    let screenshot = request.image.as_deref().map(decode_data_url).transpose()?;
+    // A broken favicon isn't worth losing the whole page over
+    let favicon = request
+        .favicon
+        .as_deref()
+        .and_then(|favicon| decode_data_url(favicon).ok());
 
-    let reader = Cursor::new(base64::decode(base64)?);
-    let width_and_height = create_thumbnail(url_hash.as_str(), reader, mime::IMAGE_PNG)?;
+    let existing = metadata::get_metadata_by_hashes(std::slice::from_ref(&url_hash))
+        .await?
+        .pop();
+    let is_new = existing.is_none();
+    let metadata = match existing {
+        // The existing item keeps its thumbnail and favicon, only what was read from the page changes
+        Some(existing) => update_saved_again(request, existing).await?,
+        None => {
+            let timestamp_created = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_secs() as i32;
 
-    let timestamp_created = SystemTime::now()
+            let mut metadata = Metadata {
+                hash: url_hash,
+                name: request.name.clone(),
+                path: request.url.clone(),
+                content_type: ContentType::Link,
+                status: metadata::Status::Valid,
+                timestamp_created,
+                timestamp_modified: timestamp_created,
+                extension: None,
+                tags: None,
+                notes: None,
+                width: None,
+                height: None,
+                duration: None,
+                rating: 0,
+                favorite: false,
+                timestamp_trashed: None,
+            };
+            apply_popup_input(request, &mut metadata);
+            tag_rule::apply_tag_rules(&tag_rule::get_enabled_tag_rules()?, &mut metadata);
+
+            metadata::insert_metadata(metadata.clone()).await?;
+
+            // Files are only written once the item is in, so they can't end up replacing another item's
+            if let Some((mime_type, bytes)) = &favicon {
+                favicon::save_favicon(&metadata.hash, mime_type, bytes)?;
+            }
+            // Without a screenshot the favicon is the next best thing to show
+            for (mime_type, bytes) in [&screenshot, &favicon].into_iter().flatten() {
+                match create_thumbnail(&metadata.hash, Cursor::new(bytes), mime_type.clone()) {
+                    Ok((width, height)) => {
+                        metadata.width = Some(width);
+                        metadata.height = Some(height);
+                        metadata::update_metadata(metadata.clone()).await?;
+                        break;
+                    }
+                    Err(error) => {
+                        println!("Couldn't create thumbnail for {}: {}", metadata.path, error)
+                    }
+                }
+            }
+
+            metadata
+        }
+    };
+
+    // The page's text and description are what make a link findable by its content
+    if let Some(text) = request.text.as_deref().map(str::trim) {
+        if !text.is_empty() {
+            content_text::insert_content_text(ContentText {
+                hash: metadata.hash.clone(),
+                body: text.to_owned(),
+            })
+            .await?;
+        }
+    }
+    store_page_info(
+        &metadata.hash,
+        [
+            ("description", request.description.as_deref()),
+            ("selection", request.selection.as_deref()),
+        ],
+    )
+    .await?;
+
+    if is_new {
+        searcher::index_metadata(&[metadata])?;
+    } else {
+        searcher::update_metadata(&[metadata])?;
+    }
+
+    // TODO: here we want to tell the extension that we added a new content and open a popup to ask the user if they wanna focus it
+
+    Ok(())
+}
+
+/// Big enough for any image worth saving, small enough that a bad link can't fill up the disk
+const MAX_DOWNLOAD_SIZE: u64 = 100 * 1024 * 1024;
+/// Downloads block the extension listener, a stalled server must not hold up later messages
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let too_large = || {
+        FocError::Extension(format!(
+            "{} is larger than {} MB",
+            url,
+            MAX_DOWNLOAD_SIZE / 1024 / 1024
+        ))
+    };
+
+    let agent = ureq::AgentBuilder::new().timeout(DOWNLOAD_TIMEOUT).build();
+    let response = agent
+        .get(url)
+        .call()
+        .map_err(|error| FocError::Extension(format!("Couldn't download {}: {}", url, error)))?;
+
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok());
+    if content_length.map_or(false, |length| length > MAX_DOWNLOAD_SIZE) {
+        return Err(too_large());
+    }
+
+    // One byte over the limit is enough to tell that the file got cut off
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_SIZE + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_DOWNLOAD_SIZE {
+        return Err(too_large());
+    }
+
+    Ok(bytes)
+}
+
+/// Saves an image from a page into the saved folder and adds it like any other file,
+/// remembering the image's address and the page it was on. An image that's already saved is
+/// updated with the popup's input and brought back from the trash instead.
+pub async fn add_image_from_extension(request: &Request) -> Result<()> {
+    let bytes = match (&request.image, &request.source_url) {
+        (Some(image), _) => decode_data_url(image)?.1,
+        (None, Some(source_url)) => download(source_url)?,
+        (None, None) => {
+            return Err(FocError::Extension(
+                "The extension didn't send an image to save".to_owned(),
+            ))
+        }
+    };
+    let kind = infer::get(&bytes)
+        .filter(|kind| kind.matcher_type() == infer::MatcherType::Image)
+        .ok_or_else(|| FocError::Extension("Only images can be saved this way".to_owned()))?;
+
+    let image_hash: String = Sha256::digest(&bytes)
+        .to_vec()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    if let Some(existing) = metadata::get_metadata_by_hashes(std::slice::from_ref(&image_hash))
+        .await?
+        .pop()
+    {
+        // The same image from another page doesn't make a new item
+        let metadata = update_saved_again(request, existing).await?;
+        store_image_page_info(request, &metadata.hash).await?;
+        return searcher::update_metadata(&[metadata]);
+    }
+
+    // Image addresses usually end in a meaningful file name, the page title is the fallback
+    let name = request
+        .source_url
+        .as_deref()
+        .and_then(|source_url| source_url.split(['?', '#']).next())
+        .and_then(|source_url| source_url.rsplit('/').next())
+        .and_then(|file_name| Path::new(file_name).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.trim().is_empty())
+        .unwrap_or_else(|| request.name.clone());
+
+    let saved_dir = get_dir_path(Dir::Saved)?;
+    let file_stem = sanitize_file_name(&name);
+    let mut file_path = saved_dir.join(format!("{}.{}", file_stem, kind.extension()));
+    let mut copy_number = 1;
+    while file_path.exists() {
+        file_path = saved_dir.join(format!(
+            "{} ({}).{}",
+            file_stem,
+            copy_number,
+            kind.extension()
+        ));
+        copy_number += 1;
+    }
+    let tag_rules = tag_rule::get_enabled_tag_rules()?;
+    std::fs::write(&file_path, &bytes)?;
+
+    let saved_path = file_path.to_string_lossy().into_owned();
+    let mut metadata = match store_file(saved_path, &tag_rules).await {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            // Otherwise retrying would leave another copy of the image next to this one,
+            // or find the half-stored item and think the image was already saved
+            let image_hashes = vec![image_hash];
+            remove_items(&image_hashes).await?;
+            thumbnail::delete_thumbnails(&image_hashes)?;
+            let _ = std::fs::remove_file(&file_path);
+            return Err(error);
+        }
+    };
+    if request.tags.is_some() || request.notes.is_some() {
+        apply_popup_input(request, &mut metadata);
+        metadata::update_metadata(metadata.clone()).await?;
+    }
+    store_image_page_info(request, &metadata.hash).await?;
+
+    searcher::index_metadata(&[metadata])?;
+
+    Ok(())
+}
+
+async fn store_image_page_info(request: &Request, hash: &str) -> Result<()> {
+    store_page_info(
+        hash,
+        [
+            ("source_url", request.source_url.as_deref()),
+            ("page_url", Some(request.url.as_str())),
+        ],
+    )
+    .await
+}
+
+/// Saves the text selected on a page as a snippet named after the page
+pub async fn add_selection_from_extension(request: &Request) -> Result<()> {
+    let selection = request
+        .selection
+        .as_deref()
+        .map(str::trim)
+        .filter(|selection| !selection.is_empty())
+        .ok_or_else(|| FocError::Extension("Nothing was selected on the page".to_owned()))?;
+
+    let mut metadata = new_item_without_file(ContentType::Snippet, &request.name, None);
+    apply_popup_input(request, &mut metadata);
+    let metadata = store_snippet(metadata, selection.to_owned()).await?;
+    store_page_info(&metadata.hash, [("page_url", Some(request.url.as_str()))]).await?;
+
+    searcher::index_metadata(&[metadata])?;
+
+    Ok(())
+}
+
+/// A new item for content that only lives in the database, like notes and snippets.
+/// There are no file contents to hash, so the creation time keeps items apart.
+fn new_item_without_file(
+    content_type: ContentType,
+    name: &str,
+    extension: Option<String>,
+) -> Metadata {
+    let timestamp_created = current_timestamp();
+    let created_nanos = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Time went backwards")
-        .as_secs() as i32;
+        .as_nanos();
+    let kind = content_type.to_string().to_lowercase();
+
+    let item_hash: String = Sha256::digest(format!("{}:{}:{}", kind, created_nanos, name))
+        .to_vec()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
 
-    let metadata = Metadata {
-        hash: url_hash,
-        name: request.name.to_owned(),
-        path: request.url.to_owned(),
-        content_type: ContentType::Link,
+    let name = name.trim();
+    Metadata {
+        hash: item_hash.clone(),
+        name: if name.is_empty() {
+            "Untitled".to_owned()
+        } else {
+            name.to_owned()
+        },
+        path: format!("focular://{}/{}", kind, item_hash),
+        content_type,
         status: metadata::Status::Valid,
         timestamp_created,
         timestamp_modified: timestamp_created,
-        extension: None,
+        extension,
         tags: None,
         notes: None,
-        width: Some(width_and_height.0),
-        height: Some(width_and_height.1),
+        width: None,
+        height: None,
         duration: None,
-    };
+        rating: 0,
+        favorite: false,
+        timestamp_trashed: None,
+    }
+}
 
-    let metadata_for_database = metadata.clone();
+/// Creates a note that stands on its own instead of belonging to a file, the Markdown
+/// lives in the item's `notes` and it can link to other items like any other item
+#[tauri::command]
+pub async fn create_note_item(name: String, body: String) -> Result<Metadata> {
+    let mut metadata = new_item_without_file(ContentType::Note, &name, None);
+    if !body.trim().is_empty() {
+        metadata.notes = Some(body);
+    }
 
-    metadata::insert_metadata(metadata_for_database).await?;
-    searcher::index_metadata(&[metadata])?;
+    metadata::insert_metadata(metadata.clone()).await?;
+    searcher::index_metadata(&[metadata.clone()])?;
 
-    // TODO: here we want to tell the extension that we added a new content and open a popup to ask the user if they wanna focus it
+    Ok(metadata)
+}
 
-    Ok(())
+/// Lowercase extension used for a snippet's language, so "Rust" or ".RS" become "rs"
+fn normalize_language(language: Option<String>) -> Option<String> {
+    language
+        .map(|language| language.trim().trim_start_matches('.').to_lowercase())
+        .filter(|language| !language.is_empty())
 }
 
-fn generate_thumbnail_from_file(metadata: &Metadata, file: &File) -> Result<()> {
-    if let ContentType::Image = metadata.content_type {
-        let image_type = match metadata.extension.as_deref() {
-            Some("png") => mime::IMAGE_PNG,
-            Some("jpg") => mime::IMAGE_JPEG,
-            Some("bmp") => mime::IMAGE_BMP,
-            Some("webp") => mime::IMAGE_PNG,
-            Some("gif") => return Ok(()),
-            _ => {
-                return Err(FocError::Metadata(
-                    "Unsupported image type detected while generating thumbnail".to_owned(),
-                ))
-            }
-        };
+/// Saves a piece of text or code as an item of its own. The text is kept as the item's
+/// content so it's searchable, and `language` is stored as its extension.
+#[tauri::command]
+pub async fn create_snippet(
+    name: String,
+    text: String,
+    language: Option<String>,
+) -> Result<Metadata> {
+    if text.trim().is_empty() {
+        return Err(FocError::Metadata("Snippet can't be empty".to_owned()));
+    }
+
+    let metadata = new_item_without_file(ContentType::Snippet, &name, normalize_language(language));
+    let metadata = store_snippet(metadata, text).await?;
+    searcher::index_metadata(std::slice::from_ref(&metadata))?;
+
+    Ok(metadata)
+}
+
+/// Stores a new snippet and its text, indexing it is up to the caller
+async fn store_snippet(mut metadata: Metadata, text: String) -> Result<Metadata> {
+    tag_rule::apply_tag_rules(&tag_rule::get_enabled_tag_rules()?, &mut metadata);
 
-        let reader = BufReader::new(file);
-        create_thumbnail(metadata.hash.as_str(), reader, image_type)?;
+    metadata::insert_metadata(metadata.clone()).await?;
+    content_text::insert_content_text(ContentText {
+        hash: metadata.hash.clone(),
+        body: text,
+    })
+    .await?;
+
+    Ok(metadata)
+}
+
+#[tauri::command]
+pub async fn get_snippet_text(hash: String) -> Result<String> {
+    content_text::get_content_text(&hash)?
+        .ok_or_else(|| FocError::Metadata(format!("Snippet {} has no text", hash)))
+}
+
+/// Replaces the text of a snippet, the old text can be brought back with undo
+#[tauri::command]
+pub async fn update_snippet_text(hash: String, text: String) -> Result<Metadata> {
+    if text.trim().is_empty() {
+        return Err(FocError::Metadata("Snippet can't be empty".to_owned()));
     }
 
-    Ok(())
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let updated = conn.transaction::<_, FocError, _>(|conn| {
+        let hashes = vec![hash.clone()];
+        let before = change_log::take_snapshots(conn, &hashes, true)?;
+        let mut metadata = before
+            .first()
+            .map(|snapshot| snapshot.metadata.clone())
+            .ok_or_else(|| FocError::Metadata(format!("Item {} doesn't exist", hash)))?;
+        if !matches!(metadata.content_type, ContentType::Snippet) {
+            return Err(FocError::Metadata(format!(
+                "{} isn't a snippet",
+                metadata.name
+            )));
+        }
+
+        metadata.timestamp_modified = current_timestamp().max(metadata.timestamp_modified + 1);
+        diesel::update(&metadata)
+            .set(metadata.clone())
+            .execute(conn)?;
+        diesel::replace_into(content_text_schema::table)
+            .values(ContentText {
+                hash: hash.clone(),
+                body: text,
+            })
+            .execute(conn)?;
+
+        let after = change_log::take_snapshots(conn, &hashes, true)?;
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)?;
+
+        Ok(metadata)
+    })?;
+
+    searcher::update_metadata(std::slice::from_ref(&updated))?;
+
+    Ok(updated)
 }
 
-fn create_thumbnail<R>(name: &str, reader: R, image_type: Mime) -> Result<(i32, i32)>
-where
-    R: BufRead + Seek,
-{
-    let mut thumbnails = create_thumbnails(reader, image_type, [ThumbnailSize::Large])?;
-    let thumbnail_raw = thumbnails.pop().unwrap();
-    let mut thumbnail_buf = Cursor::new(Vec::new());
-    thumbnail_raw.write_png(&mut thumbnail_buf)?;
+/// Turns a name into something every platform accepts as a file name
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches('.');
 
-    let thumbnail =
-        image::load_from_memory_with_format(thumbnail_buf.get_ref(), image::ImageFormat::Png)
-            .unwrap();
+    if sanitized.is_empty() {
+        "Untitled".to_owned()
+    } else {
+        sanitized.to_owned()
+    }
+}
 
-    let name = format!("{}.png", name);
-    let thumbnail_dir = get_dir_path(Dir::Thumbnails)?.join(name);
-    thumbnail.save(&thumbnail_dir)?;
+/// Writes snippets and notes out as files in `directory`, snippets keep their language as
+/// the extension and notes become Markdown files. Items that already are files are skipped.
+/// Returns the paths of the written files.
+#[tauri::command]
+pub async fn export_content(hashes: Vec<String>, directory: String) -> Result<Vec<String>> {
+    let directory = Path::new(&directory);
+    if !directory.is_dir() {
+        return Err(FocError::Metadata(format!(
+            "{} isn't a folder",
+            directory.display()
+        )));
+    }
 
-    Ok((thumbnail.width() as i32, thumbnail.height() as i32))
+    let mut exported_paths = Vec::new();
+    for metadata in metadata::get_metadata_by_hashes(&hashes).await? {
+        let (contents, extension) = match metadata.content_type {
+            ContentType::Snippet => (
+                content_text::get_content_text(&metadata.hash)?.unwrap_or_default(),
+                metadata
+                    .extension
+                    .clone()
+                    .unwrap_or_else(|| "txt".to_owned()),
+            ),
+            ContentType::Note => {
+                let mut notes: Vec<String> = metadata.notes.clone().into_iter().collect();
+                notes.extend(note::get_note_bodies_by_hash(&metadata.hash)?);
+                (notes.join("\n\n---\n\n"), "md".to_owned())
+            }
+            _ => continue,
+        };
+
+        let file_stem = sanitize_file_name(&metadata.name);
+        let mut export_path = directory.join(format!("{}.{}", file_stem, extension));
+        let mut copy_number = 1;
+        while export_path.exists() {
+            export_path = directory.join(format!("{} ({}).{}", file_stem, copy_number, extension));
+            copy_number += 1;
+        }
+
+        std::fs::write(&export_path, contents)?;
+        exported_paths.push(export_path.to_string_lossy().into_owned());
+    }
+
+    Ok(exported_paths)
 }
 
 #[tauri::command]
@@ -270,3 +1099,106 @@ pub async fn get_all_tags() -> Result<Vec<String>> {
 
     Ok(all_tags)
 }
+
+fn current_timestamp() -> i32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32
+}
+
+/// Tells a field that was left out of the patch apart from one explicitly set to `null`
+fn deserialize_present<'de, T, D>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Only the fields that are present get changed, `notes: null` clears the notes
+/// while leaving `notes` out keeps whatever is stored
+#[derive(Serialize, Deserialize, Default, Debug, Clone, TS)]
+#[ts(export)]
+pub struct MetadataPatch {
+    #[ts(optional)]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    #[ts(optional)]
+    pub tags: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    #[ts(optional)]
+    pub notes: Option<Option<String>>,
+    #[ts(optional)]
+    pub rating: Option<i32>,
+    #[ts(optional)]
+    pub favorite: Option<bool>,
+    #[ts(optional)]
+    pub status: Option<Status>,
+}
+
+/// Applies a partial update on top of the stored row. When `expected_timestamp_modified` is given
+/// and the item was changed since, nothing is written and a conflict is returned instead
+#[tauri::command]
+pub async fn patch_content(
+    hash: String,
+    patch: MetadataPatch,
+    expected_timestamp_modified: Option<i32>,
+) -> Result<Metadata> {
+    if let Some(rating) = patch.rating {
+        validate_rating(rating)?;
+    }
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let patched_metadata = conn.transaction::<_, FocError, _>(|conn| {
+        let mut metadata = metadata_schema::table
+            .find(&hash)
+            .first::<Metadata>(conn)
+            .optional()?
+            .ok_or_else(|| FocError::Metadata(format!("No item with hash {}", hash)))?;
+
+        if let Some(expected) = expected_timestamp_modified {
+            if metadata.timestamp_modified != expected {
+                return Err(FocError::Conflict(format!(
+                    "{} was modified elsewhere, reload it and try again",
+                    metadata.name
+                )));
+            }
+        }
+
+        if let Some(name) = patch.name {
+            metadata.name = name;
+        }
+        if let Some(tags) = patch.tags {
+            metadata.tags = tags.filter(|tags| !tags.is_empty());
+        }
+        if let Some(notes) = patch.notes {
+            metadata.notes = notes;
+        }
+        if let Some(rating) = patch.rating {
+            metadata.rating = rating;
+        }
+        if let Some(favorite) = patch.favorite {
+            metadata.favorite = favorite;
+        }
+        if let Some(status) = patch.status {
+            set_status(&mut metadata, status, current_timestamp());
+        }
+        // Two edits within the same second still need different timestamps to be told apart
+        metadata.timestamp_modified = current_timestamp().max(metadata.timestamp_modified + 1);
+
+        let before = change_log::take_snapshots(conn, std::slice::from_ref(&hash), false)?;
+        diesel::update(&metadata)
+            .set(metadata.clone())
+            .execute(conn)?;
+
+        let after = change_log::take_snapshots(conn, std::slice::from_ref(&hash), false)?;
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)?;
+
+        Ok(metadata)
+    })?;
+
+    searcher::update_metadata(std::slice::from_ref(&patched_metadata))?;
+
+    Ok(patched_metadata)
+}
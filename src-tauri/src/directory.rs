@@ -11,6 +11,8 @@ pub enum Dir {
     Data,
     Index,
     Thumbnails,
+    Favicons,
+    Saved,
     Config,
 }
 
@@ -21,6 +23,8 @@ impl Dir {
             Dir::Data => "data",
             Dir::Index => "index",
             Dir::Thumbnails => "thumbnails",
+            Dir::Favicons => "favicons",
+            Dir::Saved => "saved",
             Dir::Config => "config",
         }
     }
@@ -31,10 +35,14 @@ pub fn create_base_dirs() -> Result<()> {
     let data_dir = get_dir_path(Dir::Data)?;
     let index_dir = get_dir_path(Dir::Index)?;
     let thumbnails_dir = get_dir_path(Dir::Thumbnails)?;
+    let favicons_dir = get_dir_path(Dir::Favicons)?;
+    let saved_dir = get_dir_path(Dir::Saved)?;
     let config_dir = get_dir_path(Dir::Config)?;
     create_dir_all(&data_dir)?;
     create_dir_all(&index_dir)?;
     create_dir_all(&thumbnails_dir)?;
+    create_dir_all(&favicons_dir)?;
+    create_dir_all(&saved_dir)?;
     create_dir_all(&config_dir)?;
     Ok(())
 }
@@ -52,6 +60,10 @@ pub fn get_dir_path(dir: Dir) -> Result<PathBuf> {
         }
         Dir::Data => Ok(get_dir_path(Dir::Root)?.join(Dir::Data.to_string())),
         Dir::Index => Ok(get_dir_path(Dir::Root)?.join(Dir::Index.to_string())),
+        // Unlike thumbnails these can't be made again, so they don't go in the cache dir
+        Dir::Favicons => Ok(get_dir_path(Dir::Data)?.join(Dir::Favicons.to_string())),
+        // Files saved from the web extension have no other home on disk
+        Dir::Saved => Ok(get_dir_path(Dir::Data)?.join(Dir::Saved.to_string())),
         Dir::Thumbnails => {
             let cache_dir = match cache_dir() {
                 Some(cache_dir) => cache_dir,
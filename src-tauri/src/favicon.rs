@@ -0,0 +1,71 @@
+use crate::{
+    directory::{get_dir_path, Dir},
+    foc_error::Result,
+};
+use mime::Mime;
+use std::{io::ErrorKind, path::PathBuf};
+
+const FAVICON_EXTENSIONS: [&str; 6] = ["png", "ico", "svg", "jpg", "gif", "webp"];
+
+fn favicon_extension(mime_type: &Mime) -> Option<&'static str> {
+    if mime_type.type_() != mime::IMAGE {
+        return None;
+    }
+
+    match mime_type.subtype().as_str() {
+        "png" => Some("png"),
+        "x-icon" | "vnd.microsoft.icon" => Some("ico"),
+        "svg" => Some("svg"),
+        "jpeg" => Some("jpg"),
+        "gif" => Some("gif"),
+        "webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+fn favicon_path(hash: &str, extension: &str) -> Result<PathBuf> {
+    Ok(get_dir_path(Dir::Favicons)?.join(format!("{}.{}", hash, extension)))
+}
+
+fn find_favicon(hash: &str) -> Result<Option<PathBuf>> {
+    for extension in FAVICON_EXTENSIONS {
+        let path = favicon_path(hash, extension)?;
+        if path.exists() {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Stores the icon of a saved website, icons in formats we don't know are ignored
+pub fn save_favicon(hash: &str, mime_type: &Mime, bytes: &[u8]) -> Result<()> {
+    let extension = match favicon_extension(mime_type) {
+        Some(extension) => extension,
+        None => return Ok(()),
+    };
+
+    delete_favicons(&[hash.to_owned()])?;
+    std::fs::write(favicon_path(hash, extension)?, bytes)?;
+
+    Ok(())
+}
+
+pub fn delete_favicons(hashes: &[String]) -> Result<()> {
+    for hash in hashes {
+        if let Some(path) = find_favicon(hash)? {
+            match std::fs::remove_file(path) {
+                Ok(_) => {}
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_favicon_path(hash: String) -> Result<Option<String>> {
+    Ok(find_favicon(&hash)?.map(|path| path.to_string_lossy().into_owned()))
+}
@@ -1,27 +1,224 @@
-use crate::foc_error::Result;
+use crate::{
+    audio::read_audio_tags,
+    foc_error::Result,
+    models::metadata::{ContentType, CODE_EXTENSIONS, TEXT_EXTENSIONS},
+    video_container::{parse_video_info, VideoInfo},
+};
 use audio_video_metadata::{get_format_from_file, Metadata};
+use exif::{Exif, In, Reader, Tag, Value};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+};
 
+// Anything beyond this is unlikely to improve search results and only bloats the index
+const MAX_EXTRACTED_TEXT_LENGTH: usize = 1_000_000;
+
+/// Dimensions as the image is meant to be displayed, so a portrait photo
+/// stored sideways with an EXIF rotation still counts as portrait
 pub fn get_image_dimensions(image_path: &str) -> Option<(i32, i32)> {
-    match image::image_dimensions(image_path) {
-        Ok(dimensions) => Some((dimensions.0 as i32, dimensions.1 as i32)),
-        Err(_) => None,
+    let (width, height) = match image::image_dimensions(image_path) {
+        Ok(dimensions) => (dimensions.0 as i32, dimensions.1 as i32),
+        Err(_) => return None,
+    };
+
+    // Orientations 5 through 8 are all rotated by 90 degrees one way or another
+    match get_exif_orientation(image_path) {
+        Some(5..=8) => Some((height, width)),
+        _ => Some((width, height)),
     }
 }
 
 pub fn get_video_dimensions(video_path: &str) -> Option<(i32, i32)> {
+    if let Some(VideoInfo {
+        width: Some(width),
+        height: Some(height),
+        ..
+    }) = parse_video_info(video_path)
+    {
+        return Some((width as i32, height as i32));
+    }
+
     match get_format_from_file(video_path) {
         Ok(Metadata::Video(m)) => Some((m.dimensions.width as i32, m.dimensions.height as i32)),
         _ => None,
     }
 }
 
+/// Duration in seconds, MP4 based audio files like m4a go through the container parser as well
 pub fn get_duration(media_path: &str) -> Option<i32> {
+    if let Some(duration) = parse_video_info(media_path).and_then(|info| info.duration) {
+        return Some(duration.round() as i32);
+    }
+
     match get_format_from_file(media_path) {
         Ok(Metadata::Audio(m)) => Some(m.duration.unwrap_or_default().as_secs() as i32),
+        Ok(Metadata::Video(m)) => Some(m.audio.duration.unwrap_or_default().as_secs() as i32),
         _ => None,
     }
 }
 
+fn get_video_codecs(video_path: &str) -> Vec<(String, String)> {
+    let mut codecs = Vec::new();
+
+    if let Ok(Metadata::Video(m)) = get_format_from_file(video_path) {
+        if let Some(video_codec) = m.video {
+            codecs.push(("video_codec".to_owned(), video_codec));
+        }
+        if let Some(audio_codec) = m.audio.audio {
+            codecs.push(("audio_codec".to_owned(), audio_codec));
+        }
+    }
+
+    codecs
+}
+
+/// Collects whatever the file itself knows about its content, as key/value pairs
+pub fn extract_media_info(
+    file_path: &str,
+    content_type: &ContentType,
+    extension: Option<&str>,
+) -> Vec<(String, String)> {
+    match content_type {
+        ContentType::Image => read_exif_info(file_path).unwrap_or_default(),
+        ContentType::Audio => read_audio_tags(file_path, extension).unwrap_or_default(),
+        ContentType::Video => get_video_codecs(file_path),
+        _ => Vec::new(),
+    }
+}
+
+fn read_exif(image_path: &str) -> Option<Exif> {
+    let file = File::open(image_path).ok()?;
+    Reader::new()
+        .read_from_container(&mut BufReader::new(&file))
+        .ok()
+}
+
+pub fn get_exif_orientation(image_path: &str) -> Option<u32> {
+    read_exif(image_path)?
+        .get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+fn read_exif_info(image_path: &str) -> Option<Vec<(String, String)>> {
+    let exif = read_exif(image_path)?;
+
+    let mut info = Vec::new();
+    let ascii_fields = [
+        ("camera_make", Tag::Make),
+        ("camera_model", Tag::Model),
+        ("lens_model", Tag::LensModel),
+        ("date_taken", Tag::DateTimeOriginal),
+    ];
+
+    for (key, tag) in ascii_fields {
+        let value = match exif.get_field(tag, In::PRIMARY).map(|field| &field.value) {
+            Some(Value::Ascii(values)) if !values.is_empty() => {
+                String::from_utf8_lossy(&values[0]).trim().to_owned()
+            }
+            _ => continue,
+        };
+        if !value.is_empty() {
+            info.push((key.to_owned(), value));
+        }
+    }
+
+    if let Some(orientation) = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+    {
+        info.push(("orientation".to_owned(), orientation.to_string()));
+    }
+
+    let latitude = read_gps_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S');
+    let longitude = read_gps_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W');
+    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+        info.push(("gps_latitude".to_owned(), format!("{:.6}", latitude)));
+        info.push(("gps_longitude".to_owned(), format!("{:.6}", longitude)));
+    }
+
+    Some(info)
+}
+
+/// EXIF stores coordinates as degrees, minutes and seconds with the hemisphere in a separate tag
+fn read_gps_coordinate(
+    exif: &Exif,
+    coordinate_tag: Tag,
+    reference_tag: Tag,
+    negative_reference: u8,
+) -> Option<f64> {
+    let coordinate = match &exif.get_field(coordinate_tag, In::PRIMARY)?.value {
+        Value::Rational(parts) if parts.len() == 3 => {
+            parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+
+    let is_negative = match &exif.get_field(reference_tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .and_then(|value| value.first())
+            .map_or(false, |reference| *reference == negative_reference),
+        _ => false,
+    };
+
+    if is_negative {
+        Some(-coordinate)
+    } else {
+        Some(coordinate)
+    }
+}
+
+fn read_lossy(file_path: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    File::open(file_path)
+        .ok()?
+        .take(MAX_EXTRACTED_TEXT_LENGTH as u64)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Meant for `Document`, `Text` and `Code` content, only formats known to hold text are read
+pub fn extract_text(file_path: &str, extension: Option<&str>) -> Option<String> {
+    let extension = extension?.to_lowercase();
+
+    let text = match extension.as_str() {
+        // pdf_extract panics on quite a few real-world files, which shouldn't abort the whole import
+        "pdf" => std::panic::catch_unwind(|| pdf_extract::extract_text(file_path))
+            .ok()?
+            .ok()?,
+        "html" | "htm" => {
+            let bytes = std::fs::read(file_path).ok()?;
+            html2text::from_read(bytes.as_slice(), 200)
+        }
+        "md" | "markdown" => read_lossy(file_path)?,
+        extension
+            if TEXT_EXTENSIONS.contains(&extension) || CODE_EXTENSIONS.contains(&extension) =>
+        {
+            read_lossy(file_path)?
+        }
+        // Office files, ebooks and the like are binary, reading them as text only gives garbage
+        _ => return None,
+    };
+
+    let mut text = text.trim().to_owned();
+    if text.is_empty() {
+        return None;
+    }
+
+    if text.len() > MAX_EXTRACTED_TEXT_LENGTH {
+        let mut end = MAX_EXTRACTED_TEXT_LENGTH;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+
+    Some(text)
+}
+
 #[tauri::command]
 pub fn get_file_size(file_path: &str) -> Result<u64> {
     let file_size = std::fs::metadata(file_path)?.len();
@@ -34,6 +34,14 @@ pub enum FocError {
     DieselDatabaseError(#[from] diesel::result::Error),
     #[error("Folder error: \"{0}\"")]
     Folder(String),
+    #[error(transparent)]
+    ArchiveError(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    AudioError(#[from] symphonia::core::errors::Error),
+    #[error("Conflict: \"{0}\"")]
+    Conflict(String),
+    #[error(transparent)]
+    TrashError(#[from] trash::Error),
 }
 
 impl FocError {
@@ -56,6 +64,10 @@ impl FocError {
             FocError::DbMigrationError(_) => 13,
             FocError::DieselDatabaseError(_) => 14,
             FocError::Folder(_) => 15,
+            FocError::ArchiveError(_) => 16,
+            FocError::AudioError(_) => 17,
+            FocError::Conflict(_) => 18,
+            FocError::TrashError(_) => 19,
         }
     }
 }
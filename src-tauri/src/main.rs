@@ -4,26 +4,67 @@
     windows_subsystem = "windows"
 )]
  */
+mod archive;
+mod audio;
 mod content_manager;
 mod database;
 mod directory;
+mod favicon;
 mod file_utils;
 mod foc_error;
+mod markdown;
 mod searcher;
+mod thumbnail;
+mod video_container;
 mod web_extension;
 
 use crate::{
+    archive::get_archive_entries,
     content_manager::get_all_tags,
-    models::smart_folder::{
-        add_folder, delete_folder, get_all_folders, validate_folder_name, validate_folder_path,
+    models::{
+        change_log::{get_item_history, redo_changes, undo_changes},
+        collection::{
+            add_to_collection, create_collection, delete_collection, get_collection_items,
+            get_collections, remove_from_collection, reorder_collection, update_collection,
+        },
+        custom_field::{
+            create_custom_field, delete_custom_field, get_custom_field_values, get_custom_fields,
+            set_custom_field_value, update_custom_field,
+        },
+        item_link::{get_item_links, link_items, unlink_items},
+        media_info::get_media_info,
+        note::{add_note, delete_note, get_notes, update_note},
+        saved_search::{
+            create_saved_search, delete_saved_search, get_saved_searches,
+            refresh_saved_search_counts, run_saved_search, update_saved_search,
+        },
+        smart_folder::{
+            add_folder, delete_folder, get_all_folders, validate_folder_name, validate_folder_path,
+        },
+        tag_rule::{
+            create_tag_rule, delete_tag_rule, get_tag_rules, preview_tag_rules, run_tag_rules,
+            update_tag_rule,
+        },
     },
     preferences::{get_preferences, update_preferences},
     searcher::search_content,
+    thumbnail::{
+        clean_thumbnail_cache, get_preview_path, get_thumbnail_cache_size, get_thumbnail_path,
+        regenerate_thumbnails,
+    },
 };
-use crate::{content_manager::update_content, searcher::get_metadata_by_hashes};
 use crate::{
     content_manager::{add_files, delete_content},
     directory::open_in_explorer,
+    favicon::get_favicon_path,
+};
+use crate::{
+    content_manager::{
+        bulk_update_content, create_note_item, create_snippet, empty_trash, export_content,
+        get_snippet_text, get_trashed_content, patch_content, purge_content, restore_content,
+        set_favorite, set_rating, update_content, update_snippet_text,
+    },
+    searcher::get_metadata_by_hashes,
 };
 use crate::{database::init_database, file_utils::get_file_size};
 
@@ -84,7 +125,64 @@ pub fn main() -> Result<()> {
             validate_folder_path,
             get_all_folders,
             add_folder,
-            delete_folder
+            delete_folder,
+            get_archive_entries,
+            get_thumbnail_path,
+            get_preview_path,
+            regenerate_thumbnails,
+            get_thumbnail_cache_size,
+            clean_thumbnail_cache,
+            get_media_info,
+            get_custom_fields,
+            create_custom_field,
+            update_custom_field,
+            delete_custom_field,
+            get_custom_field_values,
+            set_custom_field_value,
+            set_rating,
+            set_favorite,
+            bulk_update_content,
+            patch_content,
+            get_item_history,
+            undo_changes,
+            redo_changes,
+            restore_content,
+            purge_content,
+            get_trashed_content,
+            empty_trash,
+            get_collections,
+            create_collection,
+            update_collection,
+            delete_collection,
+            get_collection_items,
+            add_to_collection,
+            remove_from_collection,
+            reorder_collection,
+            get_saved_searches,
+            create_saved_search,
+            update_saved_search,
+            delete_saved_search,
+            run_saved_search,
+            refresh_saved_search_counts,
+            get_tag_rules,
+            create_tag_rule,
+            update_tag_rule,
+            delete_tag_rule,
+            preview_tag_rules,
+            run_tag_rules,
+            link_items,
+            unlink_items,
+            get_item_links,
+            get_notes,
+            add_note,
+            update_note,
+            delete_note,
+            create_note_item,
+            create_snippet,
+            get_snippet_text,
+            update_snippet_text,
+            export_content,
+            get_favicon_path
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
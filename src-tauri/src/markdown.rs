@@ -0,0 +1,27 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+/// Text of a Markdown document the way it reads once rendered, so the search
+/// index gets the words without `**`, `#`, link targets and the like
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut text = String::with_capacity(markdown.len());
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::SoftBreak | Event::HardBreak | Event::Rule => text.push('\n'),
+            Event::End(Tag::TableCell) => text.push(' '),
+            Event::End(
+                Tag::Paragraph
+                | Tag::Heading(..)
+                | Tag::BlockQuote
+                | Tag::CodeBlock(_)
+                | Tag::Item
+                | Tag::TableRow
+                | Tag::TableHead,
+            ) => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    text.trim().to_owned()
+}
@@ -0,0 +1,583 @@
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql},
+    prelude::*,
+    serialize::{self, Output, ToSql},
+    sql_types::{self, Text},
+    AsExpression, FromSqlRow,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::{
+        collection::CollectionItem,
+        content_text::ContentText,
+        custom_field::{CustomField, CustomFieldValue},
+        item_link::ItemLink,
+        media_info::MediaInfo,
+        metadata::Metadata,
+        note::Note,
+    },
+    schema::{
+        change_log as change_log_schema, collection as collection_schema,
+        collection_item as collection_item_schema, content_text as content_text_schema,
+        custom_field as custom_field_schema, custom_field_value as custom_field_value_schema,
+        item_link as item_link_schema, media_info as media_info_schema,
+        metadata as metadata_schema, note as note_schema,
+    },
+    searcher,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum ChangeOperation {
+    Update,
+    Delete,
+    DeleteField,
+}
+
+impl ChangeOperation {
+    pub fn from_string(operation: &str) -> Result<Self> {
+        match operation {
+            "Update" => Ok(ChangeOperation::Update),
+            "Delete" => Ok(ChangeOperation::Delete),
+            "DeleteField" => Ok(ChangeOperation::DeleteField),
+            _ => Err(FocError::Database("Invalid change operation".to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Display for ChangeOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Default for ChangeOperation {
+    fn default() -> Self {
+        ChangeOperation::Update
+    }
+}
+
+pub struct TextToChangeOperation(ChangeOperation);
+impl From<TextToChangeOperation> for ChangeOperation {
+    fn from(s: TextToChangeOperation) -> Self {
+        s.0
+    }
+}
+impl<DB> Queryable<Text, DB> for TextToChangeOperation
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    type Row = String;
+
+    fn build(s: String) -> deserialize::Result<Self> {
+        Ok(TextToChangeOperation(ChangeOperation::from_string(&s)?))
+    }
+}
+
+#[derive(Debug, FromSqlRow, AsExpression)]
+#[diesel(sql_type = sql_types::Text)]
+pub struct ChangeOperationToText(pub String);
+impl From<ChangeOperation> for ChangeOperationToText {
+    fn from(operation: ChangeOperation) -> Self {
+        ChangeOperationToText(operation.to_string())
+    }
+}
+impl<DB> ToSql<sql_types::Text, DB> for ChangeOperationToText
+where
+    DB: Backend,
+    String: ToSql<sql_types::Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.0.to_sql(out)
+    }
+}
+
+/// One item's state before and after an operation, stored as JSON snapshots.
+/// All entries written by the same command share a `batch_id` and are undone together.
+#[derive(Serialize, Deserialize, Queryable, Identifiable, Default, Clone, Debug)]
+#[diesel(table_name = crate::schema::change_log)]
+#[derive(TS)]
+#[ts(export)]
+pub struct ChangeLogEntry {
+    pub id: i32,
+    pub batch_id: i32,
+    pub hash: String,
+    #[diesel(deserialize_as = TextToChangeOperation)]
+    pub operation: ChangeOperation,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub timestamp: i32,
+    pub undone: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::change_log)]
+struct NewChangeLogEntry {
+    #[diesel(column_name = batchId)]
+    batch_id: i32,
+    hash: String,
+    #[diesel(serialize_as = ChangeOperationToText)]
+    operation: ChangeOperation,
+    before: Option<String>,
+    after: Option<String>,
+    timestamp: i32,
+}
+
+/// Everything needed to put an item back the way it was. Extracted text, media info and what's
+/// attached to the item from other tables never change through edits, so they're only captured
+/// when an item gets deleted. Thumbnails and favicons aren't part of it, those files are kept
+/// around for as long as the item has a history. The definitions of the fields the item has
+/// values for are kept so that undoing the deletion of a field can bring it back.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ItemSnapshot {
+    pub metadata: Metadata,
+    pub custom_fields: Vec<CustomFieldValue>,
+    #[serde(default)]
+    pub custom_field_definitions: Vec<CustomField>,
+    pub content_text: Option<String>,
+    pub media_info: Option<Vec<MediaInfo>>,
+    pub collection_items: Option<Vec<CollectionItem>>,
+    pub item_links: Option<Vec<ItemLink>>,
+    pub notes: Option<Vec<Note>>,
+}
+
+pub fn take_snapshots(
+    conn: &mut SqliteConnection,
+    hashes: &[String],
+    include_content: bool,
+) -> Result<Vec<ItemSnapshot>> {
+    let all_metadata = metadata_schema::table
+        .filter(metadata_schema::hash.eq_any(hashes))
+        .load::<Metadata>(conn)?;
+    let all_fields = custom_field_schema::table.load::<CustomField>(conn)?;
+
+    let mut snapshots = Vec::with_capacity(all_metadata.len());
+    for metadata in all_metadata {
+        let hash = metadata.hash.clone();
+        let custom_fields = custom_field_value_schema::table
+            .filter(custom_field_value_schema::hash.eq(&hash))
+            .load::<CustomFieldValue>(conn)?;
+        let custom_field_definitions = all_fields
+            .iter()
+            .filter(|field| custom_fields.iter().any(|value| value.field_id == field.id))
+            .cloned()
+            .collect();
+
+        let mut snapshot = ItemSnapshot {
+            metadata,
+            custom_fields,
+            custom_field_definitions,
+            content_text: None,
+            media_info: None,
+            collection_items: None,
+            item_links: None,
+            notes: None,
+        };
+
+        if include_content {
+            snapshot.content_text = content_text_schema::table
+                .find(&hash)
+                .select(content_text_schema::body)
+                .first::<String>(conn)
+                .optional()?;
+            snapshot.media_info = Some(
+                media_info_schema::table
+                    .filter(media_info_schema::hash.eq(&hash))
+                    .load::<MediaInfo>(conn)?,
+            );
+            snapshot.collection_items = Some(
+                collection_item_schema::table
+                    .filter(collection_item_schema::hash.eq(&hash))
+                    .load::<CollectionItem>(conn)?,
+            );
+            snapshot.item_links = Some(
+                item_link_schema::table
+                    .filter(
+                        item_link_schema::sourceHash
+                            .eq(&hash)
+                            .or(item_link_schema::targetHash.eq(&hash)),
+                    )
+                    .load::<ItemLink>(conn)?,
+            );
+            snapshot.notes = Some(
+                note_schema::table
+                    .filter(note_schema::hash.eq(&hash))
+                    .load::<Note>(conn)?,
+            );
+        }
+
+        snapshots.push(snapshot);
+    }
+
+    Ok(snapshots)
+}
+
+/// Writes one batch for the operation, items whose snapshots didn't change are left out.
+/// Recording anything new drops the undone entries, since they can't be redone on top of it.
+pub fn record_changes(
+    conn: &mut SqliteConnection,
+    operation: ChangeOperation,
+    before: Vec<ItemSnapshot>,
+    after: Vec<ItemSnapshot>,
+) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    let mut entries = Vec::with_capacity(before.len());
+    for before_snapshot in before {
+        let after_snapshot = after
+            .iter()
+            .find(|snapshot| snapshot.metadata.hash == before_snapshot.metadata.hash);
+
+        let before_json = serde_json::to_string(&before_snapshot)?;
+        let after_json = after_snapshot.map(serde_json::to_string).transpose()?;
+        if after_json.as_deref() == Some(before_json.as_str()) {
+            continue;
+        }
+
+        entries.push((
+            before_snapshot.metadata.hash.clone(),
+            Some(before_json),
+            after_json,
+        ));
+    }
+
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    diesel::delete(change_log_schema::table.filter(change_log_schema::undone.eq(true)))
+        .execute(conn)?;
+
+    let last_batch_id = change_log_schema::table
+        .select(diesel::dsl::max(change_log_schema::batchId))
+        .first::<Option<i32>>(conn)?;
+    let batch_id = last_batch_id.unwrap_or(0) + 1;
+
+    let new_entries: Vec<NewChangeLogEntry> = entries
+        .into_iter()
+        .map(|(hash, before, after)| NewChangeLogEntry {
+            batch_id,
+            hash,
+            operation: operation.clone(),
+            before,
+            after,
+            timestamp,
+        })
+        .collect();
+
+    diesel::insert_into(change_log_schema::table)
+        .values(new_entries)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Convenience for commands that don't run inside a transaction of their own
+pub fn snapshot_items(hashes: &[String], include_content: bool) -> Result<Vec<ItemSnapshot>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    take_snapshots(&mut conn, hashes, include_content)
+}
+
+pub fn record_item_changes(
+    operation: ChangeOperation,
+    before: Vec<ItemSnapshot>,
+    after: Vec<ItemSnapshot>,
+) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    record_changes(&mut conn, operation, before, after)
+}
+
+/// Hashes of every item with a history, whether or not it still exists
+pub fn get_logged_hashes() -> Result<Vec<String>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = change_log_schema::table
+        .select(change_log_schema::hash)
+        .distinct()
+        .load::<String>(&mut conn)?;
+
+    Ok(results)
+}
+
+/// Drops every entry about the items, for items that are gone for good
+pub fn delete_item_history(hashes: &[String]) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(change_log_schema::table.filter(change_log_schema::hash.eq_any(hashes)))
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Puts an item into the state of the snapshot, `None` means the item didn't exist.
+/// With `restore_fields` the item's fields that were deleted in the meantime are created again.
+fn apply_snapshot(
+    conn: &mut SqliteConnection,
+    hash: &str,
+    snapshot: Option<ItemSnapshot>,
+    restore_fields: bool,
+) -> Result<Option<Metadata>> {
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => {
+            diesel::delete(metadata_schema::table.find(hash)).execute(conn)?;
+            diesel::delete(
+                custom_field_value_schema::table.filter(custom_field_value_schema::hash.eq(hash)),
+            )
+            .execute(conn)?;
+            diesel::delete(content_text_schema::table.find(hash)).execute(conn)?;
+            diesel::delete(media_info_schema::table.filter(media_info_schema::hash.eq(hash)))
+                .execute(conn)?;
+            diesel::delete(
+                collection_item_schema::table.filter(collection_item_schema::hash.eq(hash)),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                item_link_schema::table.filter(
+                    item_link_schema::sourceHash
+                        .eq(hash)
+                        .or(item_link_schema::targetHash.eq(hash)),
+                ),
+            )
+            .execute(conn)?;
+            diesel::delete(note_schema::table.filter(note_schema::hash.eq(hash))).execute(conn)?;
+            return Ok(None);
+        }
+    };
+
+    // Going back to the old timestamp would let a copy taken before the undone edit pass the
+    // check in `patch_content` again, so the replayed state counts as a new modification
+    let mut metadata = snapshot.metadata;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+    let stored_timestamp = metadata_schema::table
+        .find(hash)
+        .select(metadata_schema::timestampModified)
+        .first::<i32>(conn)
+        .optional()?
+        .unwrap_or(metadata.timestamp_modified);
+    metadata.timestamp_modified = now.max(stored_timestamp + 1);
+
+    diesel::replace_into(metadata_schema::table)
+        .values(metadata.clone())
+        .execute(conn)?;
+
+    // Values of fields deleted in any other way, or whose name went to a new field since, are dropped
+    if restore_fields && !snapshot.custom_field_definitions.is_empty() {
+        diesel::insert_or_ignore_into(custom_field_schema::table)
+            .values(snapshot.custom_field_definitions)
+            .execute(conn)?;
+    }
+    let existing_field_ids = custom_field_schema::table
+        .select(custom_field_schema::id)
+        .load::<i32>(conn)?;
+    let custom_fields: Vec<CustomFieldValue> = snapshot
+        .custom_fields
+        .into_iter()
+        .filter(|value| existing_field_ids.contains(&value.field_id))
+        .collect();
+    diesel::delete(
+        custom_field_value_schema::table.filter(custom_field_value_schema::hash.eq(hash)),
+    )
+    .execute(conn)?;
+    if !custom_fields.is_empty() {
+        diesel::insert_into(custom_field_value_schema::table)
+            .values(custom_fields)
+            .execute(conn)?;
+    }
+
+    if let Some(body) = snapshot.content_text {
+        diesel::replace_into(content_text_schema::table)
+            .values(ContentText {
+                hash: hash.to_owned(),
+                body,
+            })
+            .execute(conn)?;
+    }
+    if let Some(media_info) = snapshot.media_info.filter(|info| !info.is_empty()) {
+        diesel::replace_into(media_info_schema::table)
+            .values(media_info)
+            .execute(conn)?;
+    }
+
+    if let Some(collection_items) = snapshot.collection_items {
+        let existing_collection_ids = collection_schema::table
+            .select(collection_schema::id)
+            .load::<i32>(conn)?;
+        let collection_items: Vec<CollectionItem> = collection_items
+            .into_iter()
+            .filter(|item| existing_collection_ids.contains(&item.collection_id))
+            .collect();
+        if !collection_items.is_empty() {
+            diesel::replace_into(collection_item_schema::table)
+                .values(collection_items)
+                .execute(conn)?;
+        }
+    }
+
+    // A link only comes back once the item on its other end exists too,
+    // items deleted together get their links back from whichever is restored last
+    for link in snapshot.item_links.unwrap_or_default() {
+        let other_hash = if link.source_hash == hash {
+            &link.target_hash
+        } else {
+            &link.source_hash
+        };
+        let other_exists = metadata_schema::table
+            .find(other_hash)
+            .count()
+            .get_result::<i64>(conn)?
+            > 0;
+        if other_exists {
+            diesel::replace_into(item_link_schema::table)
+                .values(link)
+                .execute(conn)?;
+        }
+    }
+
+    if let Some(notes) = snapshot.notes.filter(|notes| !notes.is_empty()) {
+        diesel::replace_into(note_schema::table)
+            .values(notes)
+            .execute(conn)?;
+    }
+
+    Ok(Some(metadata))
+}
+
+fn parse_snapshot(json: Option<&str>) -> Result<Option<ItemSnapshot>> {
+    Ok(json.map(serde_json::from_str::<ItemSnapshot>).transpose()?)
+}
+
+/// Fields that the item had values for before the entry and no longer has after it
+fn deleted_fields(entry: &ChangeLogEntry) -> Result<Vec<i32>> {
+    let field_ids = |json: Option<&str>| -> Result<Vec<i32>> {
+        Ok(parse_snapshot(json)?
+            .map(|snapshot| snapshot.custom_field_definitions)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|field| field.id)
+            .collect())
+    };
+
+    let after = field_ids(entry.after.as_deref())?;
+    Ok(field_ids(entry.before.as_deref())?
+        .into_iter()
+        .filter(|id| !after.contains(id))
+        .collect())
+}
+
+/// Walks through up to `count` batches, restoring either the `before` or the `after` side
+/// of every entry, and returns the hashes of all items that changed
+async fn replay_batches(count: u32, undo: bool) -> Result<Vec<String>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let (restored, removed) = conn.transaction::<_, FocError, _>(|conn| {
+        // Undone batches always form the tail of the log, so undo walks backwards
+        // from the newest active batch and redo forwards from the oldest undone one
+        let batch_query = change_log_schema::table
+            .filter(change_log_schema::undone.eq(!undo))
+            .select(change_log_schema::batchId)
+            .distinct();
+        let batch_ids = if undo {
+            batch_query
+                .order(change_log_schema::batchId.desc())
+                .limit(count.into())
+                .load::<i32>(conn)?
+        } else {
+            batch_query
+                .order(change_log_schema::batchId.asc())
+                .limit(count.into())
+                .load::<i32>(conn)?
+        };
+
+        let mut restored: Vec<Metadata> = Vec::new();
+        let mut removed: Vec<String> = Vec::new();
+        for batch_id in batch_ids {
+            let entries = change_log_schema::table
+                .filter(change_log_schema::batchId.eq(batch_id))
+                .load::<ChangeLogEntry>(conn)?;
+
+            let mut deleted_field_ids: Vec<i32> = Vec::new();
+            for entry in entries {
+                let is_field_deletion = entry.operation == ChangeOperation::DeleteField;
+                if is_field_deletion && !undo {
+                    deleted_field_ids.extend(deleted_fields(&entry)?);
+                }
+
+                let target = if undo { &entry.before } else { &entry.after };
+                let snapshot = parse_snapshot(target.as_deref())?;
+
+                restored.retain(|metadata| metadata.hash != entry.hash);
+                removed.retain(|hash| hash != &entry.hash);
+                match apply_snapshot(conn, &entry.hash, snapshot, is_field_deletion && undo)? {
+                    Some(metadata) => restored.push(metadata),
+                    None => removed.push(entry.hash.clone()),
+                }
+            }
+
+            // The snapshots only take the values away, the field itself has to go as well
+            if !deleted_field_ids.is_empty() {
+                diesel::delete(
+                    custom_field_schema::table
+                        .filter(custom_field_schema::id.eq_any(deleted_field_ids)),
+                )
+                .execute(conn)?;
+            }
+
+            diesel::update(
+                change_log_schema::table.filter(change_log_schema::batchId.eq(batch_id)),
+            )
+            .set(change_log_schema::undone.eq(undo))
+            .execute(conn)?;
+        }
+
+        Ok((restored, removed))
+    })?;
+
+    if !removed.is_empty() {
+        searcher::delete_metadata(&removed)?;
+    }
+    if !restored.is_empty() {
+        searcher::update_metadata(&restored)?;
+    }
+
+    let mut changed_hashes: Vec<String> = restored.into_iter().map(|m| m.hash).collect();
+    changed_hashes.extend(removed);
+    Ok(changed_hashes)
+}
+
+#[tauri::command]
+pub async fn get_item_history(hash: String) -> Result<Vec<ChangeLogEntry>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = change_log_schema::table
+        .filter(change_log_schema::hash.eq(hash))
+        .order(change_log_schema::id.desc())
+        .load::<ChangeLogEntry>(&mut conn)?;
+
+    Ok(results)
+}
+
+/// Undoes the last `count` operations, one by default
+#[tauri::command]
+pub async fn undo_changes(count: Option<u32>) -> Result<Vec<String>> {
+    replay_batches(count.unwrap_or(1), true).await
+}
+
+/// Redoes the last `count` undone operations, one by default
+#[tauri::command]
+pub async fn redo_changes(count: Option<u32>) -> Result<Vec<String>> {
+    replay_batches(count.unwrap_or(1), false).await
+}
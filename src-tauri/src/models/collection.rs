@@ -0,0 +1,281 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::metadata::{Metadata, Status},
+    schema::{
+        collection as collection_schema, collection_item as collection_item_schema,
+        metadata as metadata_schema,
+    },
+};
+
+/// A hand-picked, manually ordered group of items. Unlike smart folders it has nothing to do
+/// with where files are on disk and the same item can be part of any number of collections.
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(table_name = crate::schema::collection)]
+#[diesel(treat_none_as_null = true)]
+#[derive(TS)]
+#[ts(export)]
+pub struct Collection {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    #[diesel(column_name = timestampCreated)]
+    pub timestamp_created: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::collection)]
+struct NewCollection {
+    name: String,
+    description: Option<String>,
+    #[diesel(column_name = timestampCreated)]
+    timestamp_created: i32,
+}
+
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(primary_key(collectionId, hash))]
+#[diesel(table_name = crate::schema::collection_item)]
+pub struct CollectionItem {
+    #[diesel(column_name = collectionId)]
+    pub collection_id: i32,
+    pub hash: String,
+    pub position: i32,
+}
+
+fn validate_collection_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(FocError::Metadata(
+            "Collection name can't be empty".to_owned(),
+        ));
+    }
+    Ok(name.to_owned())
+}
+
+fn get_collection_by_id(conn: &mut SqliteConnection, id_to_find: i32) -> Result<Collection> {
+    collection_schema::table
+        .find(id_to_find)
+        .first::<Collection>(conn)
+        .optional()?
+        .ok_or_else(|| FocError::Metadata(format!("Collection {} doesn't exist", id_to_find)))
+}
+
+/// Hashes of the collection's items in their manual order
+pub fn get_collection_hashes(collection_id: i32) -> Result<Vec<String>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = collection_item_schema::table
+        .filter(collection_item_schema::collectionId.eq(collection_id))
+        .order(collection_item_schema::position.asc())
+        .select(collection_item_schema::hash)
+        .load::<String>(&mut conn)?;
+
+    Ok(results)
+}
+
+pub async fn remove_from_all_collections(hashes_to_remove: &Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(
+        collection_item_schema::table.filter(collection_item_schema::hash.eq_any(hashes_to_remove)),
+    )
+    .execute(&mut conn)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_collections() -> Result<Vec<Collection>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = collection_schema::table
+        .order(collection_schema::name.asc())
+        .load::<Collection>(&mut conn)?;
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn create_collection(name: String, description: Option<String>) -> Result<Collection> {
+    let name = validate_collection_name(&name)?;
+    let timestamp_created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let created = conn.transaction::<_, FocError, _>(|conn| {
+        diesel::insert_into(collection_schema::table)
+            .values(NewCollection {
+                name,
+                description: description.filter(|description| !description.trim().is_empty()),
+                timestamp_created,
+            })
+            .execute(conn)?;
+
+        // Names aren't unique, but the newest row is the one we just inserted
+        let created = collection_schema::table
+            .order(collection_schema::id.desc())
+            .first::<Collection>(conn)?;
+
+        Ok(created)
+    })?;
+
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_collection(mut collection: Collection) -> Result<()> {
+    collection.name = validate_collection_name(&collection.name)?;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    // The creation time isn't something the frontend gets to change
+    let existing = get_collection_by_id(&mut conn, collection.id)?;
+    collection.timestamp_created = existing.timestamp_created;
+
+    let values_to_update = collection.clone();
+    diesel::update(&collection)
+        .set(values_to_update)
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_collection(id: i32) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    conn.transaction::<_, FocError, _>(|conn| {
+        diesel::delete(
+            collection_item_schema::table.filter(collection_item_schema::collectionId.eq(id)),
+        )
+        .execute(conn)?;
+        diesel::delete(collection_schema::table.find(id)).execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Items of the collection in their manual order, trashed items are left out
+#[tauri::command]
+pub async fn get_collection_items(id: i32) -> Result<Vec<Metadata>> {
+    let hashes = get_collection_hashes(id)?;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let mut items = metadata_schema::table
+        .filter(metadata_schema::hash.eq_any(&hashes))
+        .filter(metadata_schema::status.ne(Status::Trashed.to_string()))
+        .load::<Metadata>(&mut conn)?;
+    sort_by_collection_order(&mut items, &hashes);
+
+    Ok(items)
+}
+
+/// Puts the items into the order of `ordered_hashes`, which are the hashes of a collection
+pub fn sort_by_collection_order(items: &mut [Metadata], ordered_hashes: &[String]) {
+    let positions: HashMap<&str, usize> = ordered_hashes
+        .iter()
+        .enumerate()
+        .map(|(position, hash)| (hash.as_str(), position))
+        .collect();
+    items.sort_by_key(|metadata| positions.get(metadata.hash.as_str()).copied());
+}
+
+/// Appends the items to the end of the collection, items that are already in it stay where they are.
+/// Hashes of items that don't exist are skipped.
+#[tauri::command]
+pub async fn add_to_collection(collection_id: i32, hashes: Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    conn.transaction::<_, FocError, _>(|conn| {
+        get_collection_by_id(conn, collection_id)?;
+
+        let mut hashes_in_collection: HashSet<String> = collection_item_schema::table
+            .filter(collection_item_schema::collectionId.eq(collection_id))
+            .select(collection_item_schema::hash)
+            .load::<String>(conn)?
+            .into_iter()
+            .collect();
+        let known_hashes: HashSet<String> = metadata_schema::table
+            .filter(metadata_schema::hash.eq_any(&hashes))
+            .select(metadata_schema::hash)
+            .load::<String>(conn)?
+            .into_iter()
+            .collect();
+        let last_position = collection_item_schema::table
+            .filter(collection_item_schema::collectionId.eq(collection_id))
+            .select(diesel::dsl::max(collection_item_schema::position))
+            .first::<Option<i32>>(conn)?;
+
+        let mut next_position = last_position.map_or(0, |position| position + 1);
+        let mut new_items = Vec::new();
+        for hash in hashes {
+            if !known_hashes.contains(&hash) || !hashes_in_collection.insert(hash.clone()) {
+                continue;
+            }
+            new_items.push(CollectionItem {
+                collection_id,
+                hash,
+                position: next_position,
+            });
+            next_position += 1;
+        }
+
+        if !new_items.is_empty() {
+            diesel::insert_into(collection_item_schema::table)
+                .values(new_items)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn remove_from_collection(collection_id: i32, hashes: Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(
+        collection_item_schema::table
+            .filter(collection_item_schema::collectionId.eq(collection_id))
+            .filter(collection_item_schema::hash.eq_any(hashes)),
+    )
+    .execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Puts the given items first, in the given order, followed by any items of the collection
+/// that weren't mentioned, which keep their previous order
+#[tauri::command]
+pub async fn reorder_collection(collection_id: i32, hashes: Vec<String>) -> Result<()> {
+    let current_hashes = get_collection_hashes(collection_id)?;
+    let mut new_order: Vec<&String> = Vec::with_capacity(current_hashes.len());
+    for hash in hashes.iter().chain(current_hashes.iter()) {
+        if current_hashes.contains(hash) && !new_order.contains(&hash) {
+            new_order.push(hash);
+        }
+    }
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    conn.transaction::<_, FocError, _>(|conn| {
+        for (position, hash) in new_order.into_iter().enumerate() {
+            diesel::update(collection_item_schema::table.find((collection_id, hash)))
+                .set(collection_item_schema::position.eq(position as i32))
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
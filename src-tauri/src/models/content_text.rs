@@ -0,0 +1,54 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::Result,
+    schema::{self, content_text as content_text_schema},
+};
+
+/// Searchable text extracted from a piece of content, kept out of `Metadata`
+/// so that search results don't carry whole documents to the frontend.
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(primary_key(hash))]
+#[diesel(table_name = crate::schema::content_text)]
+pub struct ContentText {
+    pub hash: String,
+    pub body: String,
+}
+
+pub fn get_content_text(hash_to_find: &str) -> Result<Option<String>> {
+    use schema::content_text::dsl::*;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let result = content_text
+        .find(hash_to_find)
+        .select(body)
+        .first::<String>(&mut conn)
+        .optional()?;
+
+    Ok(result)
+}
+
+pub async fn insert_content_text(new_content_text: ContentText) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::replace_into(content_text_schema::table)
+        .values(new_content_text)
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+pub async fn delete_content_text(hashes_to_delete: &Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(
+        content_text_schema::table.filter(content_text_schema::hash.eq_any(hashes_to_delete)),
+    )
+    .execute(&mut conn)?;
+
+    Ok(())
+}
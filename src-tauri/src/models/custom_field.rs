@@ -0,0 +1,528 @@
+use std::collections::HashSet;
+
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql},
+    prelude::*,
+    serialize::{self, Output, ToSql},
+    sql_types::{self, Text},
+    AsExpression, FromSqlRow,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::{
+        change_log::{self, ChangeOperation},
+        metadata::{self, get_metadata_by_hashes, OptTextToOptVec, OptVecToOptText},
+    },
+    schema::{
+        custom_field as custom_field_schema, custom_field_value as value_schema,
+        metadata as metadata_schema,
+    },
+    searcher,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum FieldType {
+    Text,
+    Number,
+    Date,
+    Enum,
+    Url,
+}
+
+impl FieldType {
+    pub fn from_string(field_type: &str) -> Result<Self> {
+        match field_type {
+            "Text" => Ok(FieldType::Text),
+            "Number" => Ok(FieldType::Number),
+            "Date" => Ok(FieldType::Date),
+            "Enum" => Ok(FieldType::Enum),
+            "Url" => Ok(FieldType::Url),
+            _ => Err(FocError::Metadata("Invalid custom field type".to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Default for FieldType {
+    fn default() -> Self {
+        FieldType::Text
+    }
+}
+
+pub struct TextToFieldType(FieldType);
+impl From<TextToFieldType> for FieldType {
+    fn from(s: TextToFieldType) -> Self {
+        s.0
+    }
+}
+impl<DB> Queryable<Text, DB> for TextToFieldType
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    type Row = String;
+
+    fn build(s: String) -> deserialize::Result<Self> {
+        Ok(TextToFieldType(FieldType::from_string(&s)?))
+    }
+}
+
+#[derive(Debug, FromSqlRow, AsExpression)]
+#[diesel(sql_type = sql_types::Text)]
+pub struct FieldTypeToText(pub String);
+impl From<FieldType> for FieldTypeToText {
+    fn from(ft: FieldType) -> Self {
+        FieldTypeToText(ft.to_string())
+    }
+}
+impl<DB> ToSql<sql_types::Text, DB> for FieldTypeToText
+where
+    DB: Backend,
+    String: ToSql<sql_types::Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.0.to_sql(out)
+    }
+}
+
+/// A user defined field that can be filled in on any item, `options` lists
+/// the allowed values of an `Enum` field and is empty for all other types
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(table_name = crate::schema::custom_field)]
+#[diesel(treat_none_as_null = true)]
+#[derive(TS)]
+#[ts(export)]
+pub struct CustomField {
+    pub id: i32,
+    pub name: String,
+    #[diesel(serialize_as = FieldTypeToText)]
+    #[diesel(deserialize_as = TextToFieldType)]
+    #[diesel(column_name = fieldType)]
+    pub field_type: FieldType,
+    #[diesel(deserialize_as = OptTextToOptVec)]
+    #[diesel(serialize_as = OptVecToOptText)]
+    pub options: Option<Vec<String>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::custom_field)]
+struct NewCustomField {
+    name: String,
+    #[diesel(serialize_as = FieldTypeToText)]
+    #[diesel(column_name = fieldType)]
+    field_type: FieldType,
+    #[diesel(serialize_as = OptVecToOptText)]
+    options: Option<Vec<String>>,
+}
+
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(primary_key(hash, fieldId))]
+#[diesel(table_name = crate::schema::custom_field_value)]
+#[derive(TS)]
+#[ts(export)]
+pub struct CustomFieldValue {
+    pub hash: String,
+    #[diesel(column_name = fieldId)]
+    pub field_id: i32,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub enum FilterOperator {
+    Equals,
+    Contains,
+    GreaterThan,
+    LessThan,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct CustomFieldFilter {
+    pub field_id: i32,
+    pub operator: FilterOperator,
+    pub value: String,
+}
+
+/// Checks that the value fits the field and brings it into the form it's stored in,
+/// dates are kept as YYYY-MM-DD so they can be compared as plain strings
+fn normalize_value(field: &CustomField, value: &str) -> Result<String> {
+    let value = value.trim();
+    let invalid = |reason: &str| {
+        Err(FocError::Metadata(format!(
+            "Invalid value for field \"{}\": {}",
+            field.name, reason
+        )))
+    };
+
+    match field.field_type {
+        FieldType::Text => Ok(value.to_owned()),
+        FieldType::Number => match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(number.to_string()),
+            _ => invalid("expected a number"),
+        },
+        FieldType::Date => {
+            if is_valid_date(value) {
+                Ok(value.to_owned())
+            } else {
+                invalid("expected a date in the form YYYY-MM-DD")
+            }
+        }
+        FieldType::Enum => {
+            let options = field.options.as_deref().unwrap_or_default();
+            if options.iter().any(|option| option == value) {
+                Ok(value.to_owned())
+            } else {
+                invalid("not one of the allowed options")
+            }
+        }
+        FieldType::Url => {
+            let has_scheme = value.starts_with("http://") || value.starts_with("https://");
+            if has_scheme && !value.contains(char::is_whitespace) {
+                Ok(value.to_owned())
+            } else {
+                invalid("expected an http or https URL")
+            }
+        }
+    }
+}
+
+fn is_valid_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return false;
+    }
+
+    match (
+        parts[0].parse::<u32>(),
+        parts[1].parse::<u32>(),
+        parts[2].parse::<u32>(),
+    ) {
+        (Ok(_), Ok(month), Ok(day)) => (1..=12).contains(&month) && (1..=31).contains(&day),
+        _ => false,
+    }
+}
+
+fn matches_filter(field_type: &FieldType, stored: &str, filter: &CustomFieldFilter) -> bool {
+    if let FieldType::Number = field_type {
+        if let (Ok(stored), Ok(wanted)) = (stored.parse::<f64>(), filter.value.parse::<f64>()) {
+            return match filter.operator {
+                FilterOperator::Equals => stored == wanted,
+                FilterOperator::Contains => stored.to_string().contains(&filter.value),
+                FilterOperator::GreaterThan => stored > wanted,
+                FilterOperator::LessThan => stored < wanted,
+            };
+        }
+    }
+
+    let stored = stored.to_lowercase();
+    let wanted = filter.value.trim().to_lowercase();
+    match filter.operator {
+        FilterOperator::Equals => stored == wanted,
+        FilterOperator::Contains => stored.contains(&wanted),
+        FilterOperator::GreaterThan => stored > wanted,
+        FilterOperator::LessThan => stored < wanted,
+    }
+}
+
+pub fn get_custom_field_by_id(id_to_find: i32) -> Result<CustomField> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    load_custom_field(&mut conn, id_to_find)
+}
+
+fn load_custom_field(conn: &mut SqliteConnection, id_to_find: i32) -> Result<CustomField> {
+    custom_field_schema::table
+        .find(id_to_find)
+        .first::<CustomField>(conn)
+        .optional()?
+        .ok_or_else(|| FocError::Metadata(format!("Custom field {} doesn't exist", id_to_find)))
+}
+
+/// Sets or, when the value is empty, clears a field on all given items using
+/// the caller's connection so it can be part of a larger transaction
+pub fn set_custom_field_values(
+    conn: &mut SqliteConnection,
+    hashes: &[String],
+    field_id: i32,
+    value: Option<&str>,
+) -> Result<()> {
+    let field = load_custom_field(conn, field_id)?;
+
+    // Values of items that don't exist would only turn up later in searches and reindexing
+    let unique_hashes: HashSet<&String> = hashes.iter().collect();
+    let existing_count = metadata_schema::table
+        .filter(metadata_schema::hash.eq_any(hashes))
+        .count()
+        .get_result::<i64>(conn)?;
+    if existing_count != unique_hashes.len() as i64 {
+        return Err(FocError::Metadata(
+            "Custom field values can only be set on existing items".to_owned(),
+        ));
+    }
+
+    match value.filter(|new_value| !new_value.trim().is_empty()) {
+        Some(new_value) => {
+            let normalized_value = normalize_value(&field, new_value)?;
+            let new_values: Vec<CustomFieldValue> = hashes
+                .iter()
+                .map(|hash| CustomFieldValue {
+                    hash: hash.clone(),
+                    field_id: field.id,
+                    value: normalized_value.clone(),
+                })
+                .collect();
+            diesel::replace_into(value_schema::table)
+                .values(new_values)
+                .execute(conn)?;
+        }
+        None => {
+            diesel::delete(
+                value_schema::table
+                    .filter(value_schema::fieldId.eq(field.id))
+                    .filter(value_schema::hash.eq_any(hashes)),
+            )
+            .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn get_custom_field_values_by_hash(hash_to_find: &str) -> Result<Vec<CustomFieldValue>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = value_schema::table
+        .filter(value_schema::hash.eq(hash_to_find))
+        .load::<CustomFieldValue>(&mut conn)?;
+
+    Ok(results)
+}
+
+/// Hashes of all items that match every one of the filters, `None` if there's nothing to filter by.
+/// Saved searches can still name fields that were deleted since, those filters are skipped.
+pub fn find_hashes_matching(filters: &[CustomFieldFilter]) -> Result<Option<HashSet<String>>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let mut matching: Option<HashSet<String>> = None;
+
+    for filter in filters {
+        let field = match custom_field_schema::table
+            .find(filter.field_id)
+            .first::<CustomField>(&mut conn)
+            .optional()?
+        {
+            Some(field) => field,
+            None => continue,
+        };
+        let values = value_schema::table
+            .filter(value_schema::fieldId.eq(field.id))
+            .load::<CustomFieldValue>(&mut conn)?;
+
+        let hashes: HashSet<String> = values
+            .into_iter()
+            .filter(|value| matches_filter(&field.field_type, &value.value, filter))
+            .map(|value| value.hash)
+            .collect();
+
+        matching = Some(match matching {
+            Some(previous) => previous.intersection(&hashes).cloned().collect(),
+            None => hashes,
+        });
+    }
+
+    Ok(matching)
+}
+
+pub async fn delete_custom_field_values(hashes_to_delete: &Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(value_schema::table.filter(value_schema::hash.eq_any(hashes_to_delete)))
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+async fn reindex_items(hashes: Vec<String>) -> Result<()> {
+    let metadata = get_metadata_by_hashes(&hashes).await?;
+    searcher::update_metadata(&metadata)
+}
+
+fn validate_field_definition(field_type: &FieldType, options: &Option<Vec<String>>) -> Result<()> {
+    let has_options = options
+        .as_ref()
+        .map_or(false, |options| !options.is_empty());
+
+    match (field_type, has_options) {
+        (FieldType::Enum, false) => Err(FocError::Metadata(
+            "Enum fields need at least one option".to_owned(),
+        )),
+        // Options are stored separated by semicolons
+        (FieldType::Enum, true) => {
+            let options = options.as_deref().unwrap_or_default();
+            if options.iter().any(|option| option.contains(';')) {
+                Err(FocError::Metadata(
+                    "Enum options can't contain \";\"".to_owned(),
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        (_, true) => Err(FocError::Metadata(
+            "Only enum fields can have options".to_owned(),
+        )),
+        (_, false) => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_custom_fields() -> Result<Vec<CustomField>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = custom_field_schema::table
+        .order(custom_field_schema::name.asc())
+        .load::<CustomField>(&mut conn)?;
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn create_custom_field(
+    name: String,
+    field_type: FieldType,
+    options: Option<Vec<String>>,
+) -> Result<CustomField> {
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Err(FocError::Metadata(
+            "Custom field name can't be empty".to_owned(),
+        ));
+    }
+    validate_field_definition(&field_type, &options)?;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::insert_into(custom_field_schema::table)
+        .values(NewCustomField {
+            name: name.clone(),
+            field_type,
+            options,
+        })
+        .execute(&mut conn)?;
+
+    let created = custom_field_schema::table
+        .filter(custom_field_schema::name.eq(name))
+        .first::<CustomField>(&mut conn)?;
+
+    Ok(created)
+}
+
+/// Renames a field or changes its enum options, the type can't change once a field exists
+/// since the values already stored for it were validated against the old type
+#[tauri::command]
+pub async fn update_custom_field(field: CustomField) -> Result<()> {
+    let existing = get_custom_field_by_id(field.id)?;
+    if existing.field_type != field.field_type {
+        return Err(FocError::Metadata(
+            "The type of a custom field can't be changed".to_owned(),
+        ));
+    }
+    validate_field_definition(&field.field_type, &field.options)?;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    // Values that are no longer one of the options would stay searchable but could never be set again
+    let kept_options = field.options.as_deref().unwrap_or_default();
+    let removed_options: Vec<&String> = existing
+        .options
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .filter(|option| !kept_options.contains(option))
+        .collect();
+    let options_in_use = value_schema::table
+        .filter(value_schema::fieldId.eq(field.id))
+        .filter(value_schema::value.eq_any(removed_options))
+        .select(value_schema::value)
+        .distinct()
+        .load::<String>(&mut conn)?;
+    if !options_in_use.is_empty() {
+        return Err(FocError::Metadata(format!(
+            "Options still in use can't be removed: {}",
+            options_in_use.join(", ")
+        )));
+    }
+
+    let values_to_update = field.clone();
+    diesel::update(&field)
+        .set(values_to_update)
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Deletes a field with all of its values, undoing it brings back both.
+/// A field no item has a value for leaves nothing in the history.
+#[tauri::command]
+pub async fn delete_custom_field(id: i32) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let affected_hashes = conn.transaction::<_, FocError, _>(|conn| {
+        let affected_hashes = value_schema::table
+            .filter(value_schema::fieldId.eq(id))
+            .select(value_schema::hash)
+            .load::<String>(conn)?;
+
+        let before = change_log::take_snapshots(conn, &affected_hashes, false)?;
+        diesel::delete(value_schema::table.filter(value_schema::fieldId.eq(id))).execute(conn)?;
+        diesel::delete(custom_field_schema::table.find(id)).execute(conn)?;
+        metadata::touch_metadata(conn, &affected_hashes)?;
+
+        let after = change_log::take_snapshots(conn, &affected_hashes, false)?;
+        change_log::record_changes(conn, ChangeOperation::DeleteField, before, after)?;
+
+        Ok(affected_hashes)
+    })?;
+
+    reindex_items(affected_hashes).await
+}
+
+#[tauri::command]
+pub async fn get_custom_field_values(hash: String) -> Result<Vec<CustomFieldValue>> {
+    get_custom_field_values_by_hash(&hash)
+}
+
+/// Sets the value of a field on an item, an empty or missing value clears it
+#[tauri::command]
+pub async fn set_custom_field_value(
+    hash: String,
+    field_id: i32,
+    value: Option<String>,
+) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let hashes = vec![hash];
+
+    conn.transaction::<_, FocError, _>(|conn| {
+        let before = change_log::take_snapshots(conn, &hashes, false)?;
+        set_custom_field_values(conn, &hashes, field_id, value.as_deref())?;
+        metadata::touch_metadata(conn, &hashes)?;
+
+        let after = change_log::take_snapshots(conn, &hashes, false)?;
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)
+    })?;
+
+    reindex_items(hashes).await
+}
@@ -0,0 +1,342 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::metadata::{Metadata, Status},
+    schema::{item_link as item_link_schema, metadata as metadata_schema, note as note_schema},
+};
+
+/// Relation shown for references written in notes as `[[name]]`
+pub const NOTE_REFERENCE_RELATION: &str = "References";
+
+/// A link from one item to another. The relation is free-form text like "Reference for" or
+/// "Derived from", and every link shows up on both items, as a backlink on the target.
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(table_name = crate::schema::item_link)]
+#[derive(TS)]
+#[ts(export)]
+pub struct ItemLink {
+    pub id: i32,
+    #[diesel(column_name = sourceHash)]
+    pub source_hash: String,
+    #[diesel(column_name = targetHash)]
+    pub target_hash: String,
+    pub relation: String,
+    #[diesel(column_name = timestampCreated)]
+    pub timestamp_created: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::item_link)]
+struct NewItemLink {
+    #[diesel(column_name = sourceHash)]
+    source_hash: String,
+    #[diesel(column_name = targetHash)]
+    target_hash: String,
+    relation: String,
+    #[diesel(column_name = timestampCreated)]
+    timestamp_created: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum LinkDirection {
+    /// The item links to the other item
+    Outgoing,
+    /// The other item links to the item, a backlink
+    Incoming,
+}
+
+/// An item related to the one that was asked about. `link_id` is missing for
+/// references that come from notes, those go away by editing the notes.
+#[derive(Serialize, Deserialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct LinkedItem {
+    pub link_id: Option<i32>,
+    pub relation: String,
+    pub direction: LinkDirection,
+    pub hash: String,
+    pub name: String,
+}
+
+/// Names referenced in notes as `[[name]]`, in the order they first appear
+pub fn parse_note_references(notes: &str) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    let mut rest = notes;
+
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let end = match rest.find("]]") {
+            Some(end) => end,
+            None => break,
+        };
+
+        let name = rest[..end].trim();
+        if !name.is_empty() && !name.contains('\n') && !references.iter().any(|r| r == name) {
+            references.push(name.to_owned());
+        }
+        rest = &rest[end + 2..];
+    }
+
+    references
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn load_live_metadata(conn: &mut SqliteConnection, hashes: &[String]) -> Result<Vec<Metadata>> {
+    let results = metadata_schema::table
+        .filter(metadata_schema::hash.eq_any(hashes))
+        .filter(metadata_schema::status.ne(Status::Trashed.to_string()))
+        .load::<Metadata>(conn)?;
+
+    Ok(results)
+}
+
+/// Items that the notes of `metadata` reference by name, both its own `notes`
+/// and the ones added separately
+fn resolve_note_references(
+    conn: &mut SqliteConnection,
+    metadata: &Metadata,
+) -> Result<Vec<Metadata>> {
+    let note_bodies = note_schema::table
+        .filter(note_schema::hash.eq(&metadata.hash))
+        .select(note_schema::body)
+        .load::<String>(conn)?;
+
+    let mut names = parse_note_references(metadata.notes.as_deref().unwrap_or_default());
+    for body in note_bodies.iter() {
+        for name in parse_note_references(body) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let results = metadata_schema::table
+        .filter(metadata_schema::name.eq_any(&names))
+        .filter(metadata_schema::hash.ne(&metadata.hash))
+        .filter(metadata_schema::status.ne(Status::Trashed.to_string()))
+        .load::<Metadata>(conn)?;
+
+    Ok(results)
+}
+
+/// Items whose notes reference `metadata` by name, in their own `notes` or in a separate note
+fn find_note_backlinks(conn: &mut SqliteConnection, metadata: &Metadata) -> Result<Vec<Metadata>> {
+    // LIKE only narrows it down, the notes still have to be parsed to know it's a real reference
+    let name_pattern = format!("%{}%", escape_like(&metadata.name));
+    let candidates = metadata_schema::table
+        .filter(metadata_schema::notes.like(&name_pattern).escape('\\'))
+        .filter(metadata_schema::notes.like("%[[%"))
+        .filter(metadata_schema::hash.ne(&metadata.hash))
+        .filter(metadata_schema::status.ne(Status::Trashed.to_string()))
+        .load::<Metadata>(conn)?;
+    let mut backlinks: Vec<Metadata> = candidates
+        .into_iter()
+        .filter(|candidate| {
+            parse_note_references(candidate.notes.as_deref().unwrap_or_default())
+                .contains(&metadata.name)
+        })
+        .collect();
+
+    let note_candidates = note_schema::table
+        .filter(note_schema::body.like(&name_pattern).escape('\\'))
+        .filter(note_schema::body.like("%[[%"))
+        .filter(note_schema::hash.ne(&metadata.hash))
+        .select((note_schema::hash, note_schema::body))
+        .load::<(String, String)>(conn)?;
+    let mut note_hashes: Vec<String> = Vec::new();
+    for (hash, body) in note_candidates {
+        let already_found = backlinks.iter().any(|backlink| backlink.hash == hash);
+        if !already_found
+            && !note_hashes.contains(&hash)
+            && parse_note_references(&body).contains(&metadata.name)
+        {
+            note_hashes.push(hash);
+        }
+    }
+    if !note_hashes.is_empty() {
+        backlinks.extend(load_live_metadata(conn, &note_hashes)?);
+    }
+
+    Ok(backlinks)
+}
+
+pub async fn delete_item_links(hashes_to_delete: &Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(
+        item_link_schema::table.filter(
+            item_link_schema::sourceHash
+                .eq_any(hashes_to_delete)
+                .or(item_link_schema::targetHash.eq_any(hashes_to_delete)),
+        ),
+    )
+    .execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Links the source item to each of the targets, links that already exist are left alone
+#[tauri::command]
+pub async fn link_items(
+    source_hash: String,
+    target_hashes: Vec<String>,
+    relation: String,
+) -> Result<Vec<ItemLink>> {
+    let relation = relation.trim().to_owned();
+    if relation.is_empty() {
+        return Err(FocError::Metadata(
+            "Link relation can't be empty".to_owned(),
+        ));
+    }
+    if target_hashes.contains(&source_hash) {
+        return Err(FocError::Metadata(
+            "An item can't be linked to itself".to_owned(),
+        ));
+    }
+    let timestamp_created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let links = conn.transaction::<_, FocError, _>(|conn| {
+        let mut all_hashes = target_hashes.clone();
+        all_hashes.push(source_hash.clone());
+        all_hashes.sort_unstable();
+        all_hashes.dedup();
+        if load_live_metadata(conn, &all_hashes)?.len() != all_hashes.len() {
+            return Err(FocError::Metadata(
+                "Only existing items can be linked".to_owned(),
+            ));
+        }
+
+        for target_hash in target_hashes.iter() {
+            let existing = item_link_schema::table
+                .filter(item_link_schema::sourceHash.eq(&source_hash))
+                .filter(item_link_schema::targetHash.eq(target_hash))
+                .filter(item_link_schema::relation.eq(&relation))
+                .select(item_link_schema::id)
+                .first::<i32>(conn)
+                .optional()?;
+            if existing.is_none() {
+                diesel::insert_into(item_link_schema::table)
+                    .values(NewItemLink {
+                        source_hash: source_hash.clone(),
+                        target_hash: target_hash.clone(),
+                        relation: relation.clone(),
+                        timestamp_created,
+                    })
+                    .execute(conn)?;
+            }
+        }
+
+        let links = item_link_schema::table
+            .filter(item_link_schema::sourceHash.eq(&source_hash))
+            .filter(item_link_schema::targetHash.eq_any(&target_hashes))
+            .filter(item_link_schema::relation.eq(&relation))
+            .load::<ItemLink>(conn)?;
+
+        Ok(links)
+    })?;
+
+    Ok(links)
+}
+
+#[tauri::command]
+pub async fn unlink_items(link_ids: Vec<i32>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(item_link_schema::table.filter(item_link_schema::id.eq_any(link_ids)))
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Everything the item is related to in either direction, both the links made by hand
+/// and the `[[name]]` references in notes. Trashed items are left out.
+#[tauri::command]
+pub async fn get_item_links(hash: String) -> Result<Vec<LinkedItem>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let metadata = load_live_metadata(&mut conn, std::slice::from_ref(&hash))?
+        .pop()
+        .ok_or_else(|| FocError::Metadata(format!("Item {} doesn't exist", hash)))?;
+
+    let links = item_link_schema::table
+        .filter(
+            item_link_schema::sourceHash
+                .eq(&hash)
+                .or(item_link_schema::targetHash.eq(&hash)),
+        )
+        .order(item_link_schema::timestampCreated.asc())
+        .load::<ItemLink>(&mut conn)?;
+
+    let other_hashes: Vec<String> = links
+        .iter()
+        .map(|link| {
+            if link.source_hash == hash {
+                link.target_hash.clone()
+            } else {
+                link.source_hash.clone()
+            }
+        })
+        .collect();
+    let other_items = load_live_metadata(&mut conn, &other_hashes)?;
+
+    let mut linked_items: Vec<LinkedItem> = links
+        .into_iter()
+        .zip(other_hashes)
+        .filter_map(|(link, other_hash)| {
+            let item = other_items.iter().find(|item| item.hash == other_hash)?;
+            Some(LinkedItem {
+                link_id: Some(link.id),
+                direction: if link.source_hash == hash {
+                    LinkDirection::Outgoing
+                } else {
+                    LinkDirection::Incoming
+                },
+                relation: link.relation,
+                hash: item.hash.clone(),
+                name: item.name.clone(),
+            })
+        })
+        .collect();
+
+    for item in resolve_note_references(&mut conn, &metadata)? {
+        linked_items.push(LinkedItem {
+            link_id: None,
+            relation: NOTE_REFERENCE_RELATION.to_owned(),
+            direction: LinkDirection::Outgoing,
+            hash: item.hash,
+            name: item.name,
+        });
+    }
+    for item in find_note_backlinks(&mut conn, &metadata)? {
+        linked_items.push(LinkedItem {
+            link_id: None,
+            relation: NOTE_REFERENCE_RELATION.to_owned(),
+            direction: LinkDirection::Incoming,
+            hash: item.hash,
+            name: item.name,
+        });
+    }
+
+    Ok(linked_items)
+}
@@ -0,0 +1,62 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::Result,
+    schema::{self, media_info as media_info_schema},
+};
+
+/// A single piece of metadata read from the file itself, like the camera model from EXIF or the
+/// artist from ID3, kept as key/value pairs so new kinds of information don't need a migration
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(primary_key(hash, key))]
+#[diesel(table_name = crate::schema::media_info)]
+#[derive(TS)]
+#[ts(export)]
+pub struct MediaInfo {
+    pub hash: String,
+    pub key: String,
+    pub value: String,
+}
+
+pub fn get_media_info_by_hash(hash_to_find: &str) -> Result<Vec<MediaInfo>> {
+    use schema::media_info::dsl::*;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = media_info
+        .filter(hash.eq(hash_to_find))
+        .order(key.asc())
+        .load::<MediaInfo>(&mut conn)?;
+
+    Ok(results)
+}
+
+pub async fn insert_media_info(new_media_info: Vec<MediaInfo>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::replace_into(media_info_schema::table)
+        .values(new_media_info)
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+pub async fn delete_media_info(hashes_to_delete: &Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(
+        media_info_schema::table.filter(media_info_schema::hash.eq_any(hashes_to_delete)),
+    )
+    .execute(&mut conn)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_media_info(hash: String) -> Result<Vec<MediaInfo>> {
+    get_media_info_by_hash(&hash)
+}
@@ -14,6 +14,19 @@ use diesel::{
 };
 use infer::{MatcherType, Type};
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ts_rs::TS;
+
+pub const MAX_RATING: i32 = 5;
+
+/// Plain text formats, which can be read and indexed as they are
+pub const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "log", "csv", "tsv", "json", "xml", "yaml", "yml", "toml", "ini", "cfg", "conf",
+];
+pub const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "c", "h", "cpp", "hpp", "cs", "java", "kt", "go", "py", "rb", "php", "js", "jsx", "ts",
+    "tsx", "svelte", "vue", "css", "scss", "sh", "bash", "ps1", "lua", "swift", "sql",
+];
 
 pub struct OptTextToOptVec(Option<Vec<String>>);
 impl From<OptTextToOptVec> for Option<Vec<String>> {
@@ -175,6 +188,13 @@ pub struct Metadata {
     pub width: Option<i32>,
     pub height: Option<i32>,
     pub duration: Option<i32>,
+    #[serde(default)]
+    pub rating: i32,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    #[diesel(column_name = timestampTrashed)]
+    pub timestamp_trashed: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -184,22 +204,82 @@ pub enum ContentType {
     Video,
     Gif,
     Link,
+    Document,
+    Archive,
+    Font,
+    Text,
+    Code,
+    Model3D,
+    Note,
+    Snippet,
     Other,
 }
 
 impl ContentType {
-    pub fn from_kind(kind: &Type) -> Result<Self> {
+    /// Links, notes and snippets only live in the database, there's no file of ours behind them
+    pub fn has_file(&self) -> bool {
+        !matches!(
+            self,
+            ContentType::Link | ContentType::Note | ContentType::Snippet
+        )
+    }
+
+    /// Picks the content type from what `infer` found in the file, falling back
+    /// to the extension when the file wasn't recognized or its kind is too generic
+    pub fn detect(kind: Option<&Type>, extension: Option<&str>) -> Self {
+        match kind.map(ContentType::from_kind) {
+            Some(ContentType::Other) | None => ContentType::from_extension(extension),
+            Some(content_type) => content_type,
+        }
+    }
+
+    pub fn from_kind(kind: &Type) -> Self {
         match kind.matcher_type() {
             MatcherType::Image => {
                 if kind.mime_type() == "image/gif" {
-                    Ok(ContentType::Gif)
+                    ContentType::Gif
                 } else {
-                    Ok(ContentType::Image)
+                    ContentType::Image
                 }
             }
-            MatcherType::Video => Ok(ContentType::Video),
-            MatcherType::Audio => Ok(ContentType::Audio),
-            _ => Err(FocError::Extension("Unsupported file extension".to_owned())),
+            MatcherType::Video => ContentType::Video,
+            MatcherType::Audio => ContentType::Audio,
+            MatcherType::Font => ContentType::Font,
+            MatcherType::Doc | MatcherType::Book => ContentType::Document,
+            MatcherType::Text => match kind.mime_type() {
+                "text/html" => ContentType::Document,
+                "text/x-shellscript" => ContentType::Code,
+                _ => ContentType::Text,
+            },
+            // infer groups a few document formats together with archives
+            MatcherType::Archive => match kind.mime_type() {
+                "application/pdf" | "application/rtf" | "application/postscript" => {
+                    ContentType::Document
+                }
+                "application/octet-stream" => ContentType::Other,
+                _ => ContentType::Archive,
+            },
+            _ => ContentType::Other,
+        }
+    }
+
+    /// Used for files that `infer` doesn't recognize, which is the case for most plain text formats
+    pub fn from_extension(extension: Option<&str>) -> Self {
+        match extension.map(str::to_lowercase).as_deref() {
+            Some(
+                "md" | "markdown" | "html" | "htm" | "pdf" | "rtf" | "doc" | "docx" | "odt"
+                | "epub" | "pages",
+            ) => ContentType::Document,
+            Some(extension) if TEXT_EXTENSIONS.contains(&extension) => ContentType::Text,
+            Some(extension) if CODE_EXTENSIONS.contains(&extension) => ContentType::Code,
+            Some(
+                "obj" | "fbx" | "stl" | "gltf" | "glb" | "blend" | "3ds" | "dae" | "ply" | "usdz",
+            ) => ContentType::Model3D,
+            Some("ttf" | "otf" | "woff" | "woff2") => ContentType::Font,
+            Some("zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar") => {
+                ContentType::Archive
+            }
+            _ => ContentType::Other,
         }
     }
 
@@ -210,6 +290,14 @@ impl ContentType {
             "Video" => Ok(ContentType::Video),
             "Audio" => Ok(ContentType::Audio),
             "Link" => Ok(ContentType::Link),
+            "Document" => Ok(ContentType::Document),
+            "Archive" => Ok(ContentType::Archive),
+            "Font" => Ok(ContentType::Font),
+            "Text" => Ok(ContentType::Text),
+            "Code" => Ok(ContentType::Code),
+            "Model3D" => Ok(ContentType::Model3D),
+            "Note" => Ok(ContentType::Note),
+            "Snippet" => Ok(ContentType::Snippet),
             "Other" => Ok(ContentType::Other),
             _ => Err(FocError::Extension("Invalid content type".to_owned())),
         }
@@ -251,6 +339,17 @@ pub async fn get_metadata_by_hash(hash_to_find: &str) -> Result<Metadata> {
     Ok(result)
 }
 
+/// Loads many items with a single query, the order of the result is unspecified
+pub async fn get_metadata_by_hashes(hashes_to_find: &[String]) -> Result<Vec<Metadata>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = metadata_schema::table
+        .filter(metadata_schema::hash.eq_any(hashes_to_find))
+        .load::<Metadata>(&mut conn)?;
+
+    Ok(results)
+}
+
 pub async fn insert_metadata(new_metadata: Metadata) -> Result<()> {
     let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
 
@@ -271,6 +370,68 @@ pub async fn update_metadata(metadata_to_update: Metadata) -> Result<()> {
     Ok(())
 }
 
+pub async fn get_trashed_metadata() -> Result<Vec<Metadata>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = metadata_schema::table
+        .filter(metadata_schema::status.eq(Status::Trashed.to_string()))
+        .order(metadata_schema::timestampTrashed.desc())
+        .load::<Metadata>(&mut conn)?;
+
+    Ok(results)
+}
+
+pub async fn update_rating(hashes_to_update: &Vec<String>, new_rating: i32) -> Result<()> {
+    use schema::metadata::dsl::*;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    conn.transaction::<_, FocError, _>(|conn| {
+        diesel::update(metadata.filter(hash.eq_any(hashes_to_update)))
+            .set(rating.eq(new_rating))
+            .execute(conn)?;
+        touch_metadata(conn, hashes_to_update)
+    })
+}
+
+pub async fn update_favorite(hashes_to_update: &Vec<String>, new_favorite: bool) -> Result<()> {
+    use schema::metadata::dsl::*;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    conn.transaction::<_, FocError, _>(|conn| {
+        diesel::update(metadata.filter(hash.eq_any(hashes_to_update)))
+            .set(favorite.eq(new_favorite))
+            .execute(conn)?;
+        touch_metadata(conn, hashes_to_update)
+    })
+}
+
+/// Bumps `timestamp_modified` for writes that don't replace the whole row. Items that were
+/// already modified within the same second move one further, so every edit can be told apart.
+pub fn touch_metadata(conn: &mut SqliteConnection, hashes_to_touch: &[String]) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    // The recent ones go first, otherwise everything just set to now would be bumped again
+    diesel::update(
+        metadata_schema::table
+            .filter(metadata_schema::hash.eq_any(hashes_to_touch))
+            .filter(metadata_schema::timestampModified.ge(now)),
+    )
+    .set(metadata_schema::timestampModified.eq(metadata_schema::timestampModified + 1))
+    .execute(conn)?;
+    diesel::update(
+        metadata_schema::table
+            .filter(metadata_schema::hash.eq_any(hashes_to_touch))
+            .filter(metadata_schema::timestampModified.lt(now)),
+    )
+    .set(metadata_schema::timestampModified.eq(now))
+    .execute(conn)?;
+
+    Ok(())
+}
+
 pub async fn delete_metadata(hashes_to_delete: &Vec<String>) -> Result<()> {
     let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
 
@@ -291,11 +452,13 @@ pub async fn get_all_tags() -> Result<Vec<Option<Vec<String>>>> {
     Ok(results)
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
 pub enum Status {
     Valid,
     Deleted,
     Duplicate,
+    Trashed,
 }
 
 impl Default for Status {
@@ -310,6 +473,7 @@ impl Status {
             "Valid" => Ok(Status::Valid),
             "Deleted" => Ok(Status::Deleted),
             "Duplicate" => Ok(Status::Duplicate),
+            "Trashed" => Ok(Status::Trashed),
             _ => Err(FocError::Extension("Invalid status".to_owned())),
         }
     }
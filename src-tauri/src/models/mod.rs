@@ -1,2 +1,11 @@
+pub mod change_log;
+pub mod collection;
+pub mod content_text;
+pub mod custom_field;
+pub mod item_link;
+pub mod media_info;
 pub mod metadata;
+pub mod note;
+pub mod saved_search;
 pub mod smart_folder;
+pub mod tag_rule;
@@ -0,0 +1,200 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::metadata::get_metadata_by_hashes,
+    schema::note as note_schema,
+    searcher,
+};
+
+/// One of the Markdown notes attached to an item, on top of the item's own `notes`.
+/// They're listed oldest first and each one keeps track of when it was last edited.
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(table_name = crate::schema::note)]
+#[derive(TS)]
+#[ts(export)]
+pub struct Note {
+    pub id: i32,
+    pub hash: String,
+    pub body: String,
+    #[diesel(column_name = timestampCreated)]
+    pub timestamp_created: i32,
+    #[diesel(column_name = timestampModified)]
+    pub timestamp_modified: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::note)]
+struct NewNote {
+    hash: String,
+    body: String,
+    #[diesel(column_name = timestampCreated)]
+    timestamp_created: i32,
+    #[diesel(column_name = timestampModified)]
+    timestamp_modified: i32,
+}
+
+fn current_timestamp() -> i32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32
+}
+
+fn validate_note_body(body: &str) -> Result<()> {
+    if body.trim().is_empty() {
+        return Err(FocError::Metadata("Note can't be empty".to_owned()));
+    }
+    Ok(())
+}
+
+/// Indexes the item again so its notes can be searched
+async fn reindex_item(hash: &str) -> Result<()> {
+    let metadata = get_metadata_by_hashes(&[hash.to_owned()]).await?;
+    searcher::update_metadata(&metadata)?;
+    Ok(())
+}
+
+pub fn get_note_bodies_by_hash(hash_to_find: &str) -> Result<Vec<String>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = note_schema::table
+        .filter(note_schema::hash.eq(hash_to_find))
+        .order(note_schema::id.asc())
+        .select(note_schema::body)
+        .load::<String>(&mut conn)?;
+
+    Ok(results)
+}
+
+/// Note bodies of many items at once, for indexing. Items without notes are left out.
+pub fn get_note_bodies_by_hashes(
+    hashes_to_find: &[String],
+) -> Result<HashMap<String, Vec<String>>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let mut note_bodies: HashMap<String, Vec<String>> = HashMap::new();
+    // Whole library rebuilds would go over SQLite's limit of bound parameters in one query
+    for hashes in hashes_to_find.chunks(500) {
+        let results = note_schema::table
+            .filter(note_schema::hash.eq_any(hashes))
+            .order(note_schema::id.asc())
+            .select((note_schema::hash, note_schema::body))
+            .load::<(String, String)>(&mut conn)?;
+        for (hash, body) in results {
+            note_bodies.entry(hash).or_default().push(body);
+        }
+    }
+
+    Ok(note_bodies)
+}
+
+pub async fn delete_notes(hashes_to_delete: &Vec<String>) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(note_schema::table.filter(note_schema::hash.eq_any(hashes_to_delete)))
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_notes(hash: String) -> Result<Vec<Note>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = note_schema::table
+        .filter(note_schema::hash.eq(hash))
+        .order(note_schema::id.asc())
+        .load::<Note>(&mut conn)?;
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn add_note(hash: String, body: String) -> Result<Note> {
+    validate_note_body(&body)?;
+    if get_metadata_by_hashes(std::slice::from_ref(&hash))
+        .await?
+        .is_empty()
+    {
+        return Err(FocError::Metadata(format!("Item {} doesn't exist", hash)));
+    }
+    let timestamp_created = current_timestamp();
+
+    let created = {
+        let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+        conn.transaction::<_, FocError, _>(|conn| {
+            diesel::insert_into(note_schema::table)
+                .values(NewNote {
+                    hash: hash.clone(),
+                    body,
+                    timestamp_created,
+                    timestamp_modified: timestamp_created,
+                })
+                .execute(conn)?;
+
+            let created = note_schema::table
+                .order(note_schema::id.desc())
+                .first::<Note>(conn)?;
+
+            Ok(created)
+        })?
+    };
+
+    reindex_item(&hash).await?;
+
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_note(id: i32, body: String) -> Result<Note> {
+    validate_note_body(&body)?;
+
+    let updated = {
+        let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+        let mut note = note_schema::table
+            .find(id)
+            .first::<Note>(&mut conn)
+            .optional()?
+            .ok_or_else(|| FocError::Metadata(format!("Note {} doesn't exist", id)))?;
+
+        note.body = body;
+        note.timestamp_modified = current_timestamp().max(note.timestamp_modified);
+        diesel::update(&note).set(note.clone()).execute(&mut conn)?;
+        note
+    };
+
+    reindex_item(&updated.hash).await?;
+
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn delete_note(id: i32) -> Result<()> {
+    let hash = {
+        let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+        let hash = note_schema::table
+            .find(id)
+            .select(note_schema::hash)
+            .first::<String>(&mut conn)
+            .optional()?;
+        diesel::delete(note_schema::table.find(id)).execute(&mut conn)?;
+        hash
+    };
+
+    if let Some(hash) = hash {
+        reindex_item(&hash).await?;
+    }
+
+    Ok(())
+}
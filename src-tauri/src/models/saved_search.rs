@@ -0,0 +1,250 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql},
+    prelude::*,
+    serialize::{self, Output, ToSql},
+    sql_types::{self, Text},
+    AsExpression, FromSqlRow,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::metadata::Metadata,
+    schema::saved_search as saved_search_schema,
+    searcher::{self, SearchFilter},
+};
+
+pub struct TextToSearchFilter(SearchFilter);
+impl From<TextToSearchFilter> for SearchFilter {
+    fn from(s: TextToSearchFilter) -> Self {
+        s.0
+    }
+}
+impl<DB> Queryable<Text, DB> for TextToSearchFilter
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    type Row = String;
+
+    fn build(s: String) -> deserialize::Result<Self> {
+        Ok(TextToSearchFilter(serde_json::from_str(&s)?))
+    }
+}
+
+#[derive(Debug, FromSqlRow, AsExpression)]
+#[diesel(sql_type = sql_types::Text)]
+pub struct SearchFilterToText(pub String);
+impl From<SearchFilter> for SearchFilterToText {
+    fn from(filter: SearchFilter) -> Self {
+        SearchFilterToText(
+            serde_json::to_string(&filter).expect("Search filters are always valid JSON"),
+        )
+    }
+}
+impl<DB> ToSql<sql_types::Text, DB> for SearchFilterToText
+where
+    DB: Backend,
+    String: ToSql<sql_types::Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.0.to_sql(out)
+    }
+}
+
+/// A named query with its filters and sorting that is evaluated again every time it's opened,
+/// so it always shows whatever currently matches. `item_count` is the number of results from
+/// the last time it was evaluated and is only meant for showing in the sidebar.
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(table_name = crate::schema::saved_search)]
+#[derive(TS)]
+#[ts(export)]
+pub struct SavedSearch {
+    pub id: i32,
+    pub name: String,
+    pub query: String,
+    #[diesel(serialize_as = SearchFilterToText)]
+    #[diesel(deserialize_as = TextToSearchFilter)]
+    pub filter: SearchFilter,
+    #[diesel(column_name = itemCount)]
+    pub item_count: i32,
+    #[diesel(column_name = timestampCreated)]
+    pub timestamp_created: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::saved_search)]
+struct NewSavedSearch {
+    name: String,
+    query: String,
+    #[diesel(serialize_as = SearchFilterToText)]
+    filter: SearchFilter,
+    #[diesel(column_name = itemCount)]
+    item_count: i32,
+    #[diesel(column_name = timestampCreated)]
+    timestamp_created: i32,
+}
+
+fn validate_saved_search_name(
+    conn: &mut SqliteConnection,
+    name: &str,
+    own_id: Option<i32>,
+) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(FocError::Metadata(
+            "Saved search name can't be empty".to_owned(),
+        ));
+    }
+
+    let taken_by = saved_search_schema::table
+        .filter(saved_search_schema::name.eq(name))
+        .select(saved_search_schema::id)
+        .first::<i32>(conn)
+        .optional()?;
+    if taken_by.map_or(false, |id| Some(id) != own_id) {
+        return Err(FocError::Metadata(format!(
+            "A saved search named \"{}\" already exists",
+            name
+        )));
+    }
+
+    Ok(name.to_owned())
+}
+
+fn get_saved_search_by_id(conn: &mut SqliteConnection, id_to_find: i32) -> Result<SavedSearch> {
+    saved_search_schema::table
+        .find(id_to_find)
+        .first::<SavedSearch>(conn)
+        .optional()?
+        .ok_or_else(|| FocError::Metadata(format!("Saved search {} doesn't exist", id_to_find)))
+}
+
+/// Runs the search and stores how many items it found
+async fn evaluate(saved_search: &SavedSearch) -> Result<Vec<Metadata>> {
+    let results =
+        searcher::search_content(&saved_search.query, Some(saved_search.filter.clone())).await?;
+    update_item_count(saved_search)?;
+
+    Ok(results)
+}
+
+/// Stores and returns how many items the search finds. Text searches only return the
+/// best matches, so the count comes from the index rather than the results.
+fn update_item_count(saved_search: &SavedSearch) -> Result<i32> {
+    let item_count = searcher::count_content(&saved_search.query, &saved_search.filter)? as i32;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    diesel::update(saved_search_schema::table.find(saved_search.id))
+        .set(saved_search_schema::itemCount.eq(item_count))
+        .execute(&mut conn)?;
+
+    Ok(item_count)
+}
+
+#[tauri::command]
+pub async fn get_saved_searches() -> Result<Vec<SavedSearch>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = saved_search_schema::table
+        .order(saved_search_schema::name.asc())
+        .load::<SavedSearch>(&mut conn)?;
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn create_saved_search(
+    name: String,
+    query: String,
+    filter: Option<SearchFilter>,
+) -> Result<SavedSearch> {
+    let timestamp_created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    let mut created = {
+        let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+        let name = validate_saved_search_name(&mut conn, &name, None)?;
+
+        diesel::insert_into(saved_search_schema::table)
+            .values(NewSavedSearch {
+                name: name.clone(),
+                query: query.trim().to_owned(),
+                filter: filter.unwrap_or_default(),
+                item_count: 0,
+                timestamp_created,
+            })
+            .execute(&mut conn)?;
+
+        saved_search_schema::table
+            .filter(saved_search_schema::name.eq(name))
+            .first::<SavedSearch>(&mut conn)?
+    };
+
+    created.item_count = update_item_count(&created)?;
+
+    Ok(created)
+}
+
+/// Saves the new name, query and filters and returns the search with its count refreshed
+#[tauri::command]
+pub async fn update_saved_search(mut saved_search: SavedSearch) -> Result<SavedSearch> {
+    {
+        let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+        let existing = get_saved_search_by_id(&mut conn, saved_search.id)?;
+        saved_search.name =
+            validate_saved_search_name(&mut conn, &saved_search.name, Some(saved_search.id))?;
+        saved_search.query = saved_search.query.trim().to_owned();
+        saved_search.timestamp_created = existing.timestamp_created;
+
+        let values_to_update = saved_search.clone();
+        diesel::update(&saved_search)
+            .set(values_to_update)
+            .execute(&mut conn)?;
+    }
+
+    saved_search.item_count = update_item_count(&saved_search)?;
+
+    Ok(saved_search)
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(id: i32) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(saved_search_schema::table.find(id)).execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Evaluates the saved search against the current library
+#[tauri::command]
+pub async fn run_saved_search(id: i32) -> Result<Vec<Metadata>> {
+    let saved_search = {
+        let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+        get_saved_search_by_id(&mut conn, id)?
+    };
+
+    evaluate(&saved_search).await
+}
+
+/// Counts the items of every saved search again so the sidebar is up to date
+#[tauri::command]
+pub async fn refresh_saved_search_counts() -> Result<Vec<SavedSearch>> {
+    let mut saved_searches = get_saved_searches().await?;
+
+    for saved_search in saved_searches.iter_mut() {
+        saved_search.item_count = update_item_count(saved_search)?;
+    }
+
+    Ok(saved_searches)
+}
@@ -0,0 +1,426 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql},
+    prelude::*,
+    serialize::{self, Output, ToSql},
+    sql_types::{self, Text},
+    AsExpression, FromSqlRow,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    database::DATABASE_INSTANCE,
+    foc_error::{FocError, Result},
+    models::{
+        change_log::{self, ChangeOperation},
+        metadata::{ContentType, Metadata, OptTextToOptVec, OptVecToOptText, Status},
+    },
+    schema::{metadata as metadata_schema, tag_rule as tag_rule_schema},
+    searcher,
+};
+
+/// What a rule looks at when deciding whether it applies to an item
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum RuleCondition {
+    /// Files inside the folder in `pattern`, usually the path of a smart folder
+    Folder,
+    /// Files with the extension in `pattern`, ignoring case
+    Extension,
+    /// Links to the domain in `pattern` or any of its subdomains
+    Domain,
+}
+
+impl RuleCondition {
+    pub fn from_string(condition: &str) -> Result<Self> {
+        match condition {
+            "Folder" => Ok(RuleCondition::Folder),
+            "Extension" => Ok(RuleCondition::Extension),
+            "Domain" => Ok(RuleCondition::Domain),
+            _ => Err(FocError::Metadata("Invalid tag rule condition".to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Display for RuleCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Default for RuleCondition {
+    fn default() -> Self {
+        RuleCondition::Extension
+    }
+}
+
+pub struct TextToRuleCondition(RuleCondition);
+impl From<TextToRuleCondition> for RuleCondition {
+    fn from(s: TextToRuleCondition) -> Self {
+        s.0
+    }
+}
+impl<DB> Queryable<Text, DB> for TextToRuleCondition
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    type Row = String;
+
+    fn build(s: String) -> deserialize::Result<Self> {
+        Ok(TextToRuleCondition(RuleCondition::from_string(&s)?))
+    }
+}
+
+#[derive(Debug, FromSqlRow, AsExpression)]
+#[diesel(sql_type = sql_types::Text)]
+pub struct RuleConditionToText(pub String);
+impl From<RuleCondition> for RuleConditionToText {
+    fn from(condition: RuleCondition) -> Self {
+        RuleConditionToText(condition.to_string())
+    }
+}
+impl<DB> ToSql<sql_types::Text, DB> for RuleConditionToText
+where
+    DB: Backend,
+    String: ToSql<sql_types::Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.0.to_sql(out)
+    }
+}
+
+/// Adds `tags` to every item that meets the condition, both when items are added
+/// and when the rules are run on the whole library
+#[derive(
+    Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable, Default, Clone, Debug,
+)]
+#[diesel(table_name = crate::schema::tag_rule)]
+#[diesel(treat_none_as_null = true)]
+#[derive(TS)]
+#[ts(export)]
+pub struct TagRule {
+    pub id: i32,
+    pub name: String,
+    #[diesel(serialize_as = RuleConditionToText)]
+    #[diesel(deserialize_as = TextToRuleCondition)]
+    pub condition: RuleCondition,
+    pub pattern: String,
+    #[diesel(deserialize_as = OptTextToOptVec)]
+    #[diesel(serialize_as = OptVecToOptText)]
+    pub tags: Option<Vec<String>>,
+    pub enabled: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::tag_rule)]
+struct NewTagRule {
+    name: String,
+    #[diesel(serialize_as = RuleConditionToText)]
+    condition: RuleCondition,
+    pattern: String,
+    #[diesel(serialize_as = OptVecToOptText)]
+    tags: Option<Vec<String>>,
+    enabled: bool,
+}
+
+/// An item that the rules would change and the tags it would get
+#[derive(Serialize, Deserialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct TagRuleMatch {
+    pub hash: String,
+    pub name: String,
+    pub rule_ids: Vec<i32>,
+    pub added_tags: Vec<String>,
+}
+
+/// Trims the rule and makes sure it can actually match something and has tags to add
+fn validate_tag_rule(
+    name: &str,
+    condition: &RuleCondition,
+    pattern: &str,
+    tags: Option<Vec<String>>,
+) -> Result<(String, String, Option<Vec<String>>)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(FocError::Metadata(
+            "Tag rule name can't be empty".to_owned(),
+        ));
+    }
+
+    let pattern = match condition {
+        RuleCondition::Folder => pattern.trim().to_owned(),
+        RuleCondition::Extension => pattern.trim().trim_start_matches('.').to_lowercase(),
+        RuleCondition::Domain => pattern
+            .trim()
+            .trim_start_matches("www.")
+            .trim_end_matches('/')
+            .to_lowercase(),
+    };
+    if pattern.is_empty() {
+        return Err(FocError::Metadata(
+            "Tag rule pattern can't be empty".to_owned(),
+        ));
+    }
+
+    let mut cleaned_tags: Vec<String> = Vec::new();
+    for tag in tags.unwrap_or_default() {
+        let tag = tag.trim();
+        if !tag.is_empty() && !cleaned_tags.iter().any(|existing| existing == tag) {
+            cleaned_tags.push(tag.to_owned());
+        }
+    }
+    if cleaned_tags.is_empty() {
+        return Err(FocError::Metadata(
+            "Tag rule needs at least one tag to add".to_owned(),
+        ));
+    }
+
+    Ok((name.to_owned(), pattern, Some(cleaned_tags)))
+}
+
+/// Host of a url without the port, credentials or a leading "www."
+fn url_host(url: &str) -> Option<String> {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.trim_start_matches("www.").to_lowercase())
+    }
+}
+
+fn rule_matches(rule: &TagRule, metadata: &Metadata) -> bool {
+    match rule.condition {
+        RuleCondition::Folder => {
+            metadata.content_type.has_file() && Path::new(&metadata.path).starts_with(&rule.pattern)
+        }
+        RuleCondition::Extension => metadata.extension.as_deref().map_or(false, |extension| {
+            extension.eq_ignore_ascii_case(&rule.pattern)
+        }),
+        RuleCondition::Domain => {
+            matches!(metadata.content_type, ContentType::Link)
+                && url_host(&metadata.path).map_or(false, |host| {
+                    host == rule.pattern || host.ends_with(&format!(".{}", rule.pattern))
+                })
+        }
+    }
+}
+
+/// Tags the matching rules would add that the item doesn't have yet, and the ids of those rules
+fn tags_from_rules(rules: &[TagRule], metadata: &Metadata) -> (Vec<i32>, Vec<String>) {
+    let existing_tags = metadata.tags.as_deref().unwrap_or_default();
+    let mut rule_ids = Vec::new();
+    let mut added_tags: Vec<String> = Vec::new();
+
+    for rule in rules.iter().filter(|rule| rule_matches(rule, metadata)) {
+        let mut added_by_rule = false;
+        for tag in rule.tags.iter().flatten() {
+            if !existing_tags.contains(tag) && !added_tags.contains(tag) {
+                added_tags.push(tag.to_owned());
+                added_by_rule = true;
+            }
+        }
+        if added_by_rule {
+            rule_ids.push(rule.id);
+        }
+    }
+
+    (rule_ids, added_tags)
+}
+
+pub fn get_enabled_tag_rules() -> Result<Vec<TagRule>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = tag_rule_schema::table
+        .filter(tag_rule_schema::enabled.eq(true))
+        .load::<TagRule>(&mut conn)?;
+
+    Ok(results)
+}
+
+/// Adds the tags of every matching rule to an item that is about to be stored
+pub fn apply_tag_rules(rules: &[TagRule], metadata: &mut Metadata) {
+    let (_, added_tags) = tags_from_rules(rules, metadata);
+    if !added_tags.is_empty() {
+        metadata
+            .tags
+            .get_or_insert_with(Vec::new)
+            .extend(added_tags);
+    }
+}
+
+fn get_library_metadata(conn: &mut SqliteConnection) -> Result<Vec<Metadata>> {
+    let results = metadata_schema::table
+        .filter(metadata_schema::status.ne(Status::Trashed.to_string()))
+        .load::<Metadata>(conn)?;
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn get_tag_rules() -> Result<Vec<TagRule>> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let results = tag_rule_schema::table
+        .order(tag_rule_schema::name.asc())
+        .load::<TagRule>(&mut conn)?;
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn create_tag_rule(
+    name: String,
+    condition: RuleCondition,
+    pattern: String,
+    tags: Vec<String>,
+) -> Result<TagRule> {
+    let (name, pattern, tags) = validate_tag_rule(&name, &condition, &pattern, Some(tags))?;
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    let created = conn.transaction::<_, FocError, _>(|conn| {
+        diesel::insert_into(tag_rule_schema::table)
+            .values(NewTagRule {
+                name,
+                condition,
+                pattern,
+                tags,
+                enabled: true,
+            })
+            .execute(conn)?;
+
+        let created = tag_rule_schema::table
+            .order(tag_rule_schema::id.desc())
+            .first::<TagRule>(conn)?;
+
+        Ok(created)
+    })?;
+
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_tag_rule(mut rule: TagRule) -> Result<()> {
+    let (name, pattern, tags) =
+        validate_tag_rule(&rule.name, &rule.condition, &rule.pattern, rule.tags)?;
+    rule.name = name;
+    rule.pattern = pattern;
+    rule.tags = tags;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let values_to_update = rule.clone();
+    diesel::update(&rule)
+        .set(values_to_update)
+        .execute(&mut conn)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_tag_rule(id: i32) -> Result<()> {
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+
+    diesel::delete(tag_rule_schema::table.find(id)).execute(&mut conn)?;
+
+    Ok(())
+}
+
+/// Dry run that lists which items would get which tags without changing anything.
+/// Pass `rules` to try out rules that aren't saved yet, otherwise the enabled saved rules are used.
+#[tauri::command]
+pub async fn preview_tag_rules(rules: Option<Vec<TagRule>>) -> Result<Vec<TagRuleMatch>> {
+    let rules = match rules {
+        Some(rules) => rules
+            .into_iter()
+            .map(|mut rule| {
+                let (name, pattern, tags) =
+                    validate_tag_rule(&rule.name, &rule.condition, &rule.pattern, rule.tags)?;
+                rule.name = name;
+                rule.pattern = pattern;
+                rule.tags = tags;
+                Ok(rule)
+            })
+            .collect::<Result<Vec<TagRule>>>()?,
+        None => get_enabled_tag_rules()?,
+    };
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let matches = get_library_metadata(&mut conn)?
+        .into_iter()
+        .filter_map(|metadata| {
+            let (rule_ids, added_tags) = tags_from_rules(&rules, &metadata);
+            if added_tags.is_empty() {
+                return None;
+            }
+            Some(TagRuleMatch {
+                hash: metadata.hash,
+                name: metadata.name,
+                rule_ids,
+                added_tags,
+            })
+        })
+        .collect();
+
+    Ok(matches)
+}
+
+/// Runs the enabled rules on everything in the library and returns the items that got new tags.
+/// The changes are recorded as one undoable batch.
+#[tauri::command]
+pub async fn run_tag_rules() -> Result<Vec<Metadata>> {
+    let rules = get_enabled_tag_rules()?;
+    let timestamp_modified = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i32;
+
+    let mut conn = DATABASE_INSTANCE.get().unwrap().get().unwrap();
+    let updated_metadata = conn.transaction::<_, FocError, _>(|conn| {
+        let mut updated_metadata: Vec<Metadata> = get_library_metadata(conn)?
+            .into_iter()
+            .filter_map(|mut metadata| {
+                let tags_before = metadata.tags.clone();
+                apply_tag_rules(&rules, &mut metadata);
+                if metadata.tags != tags_before {
+                    Some(metadata)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if updated_metadata.is_empty() {
+            return Ok(updated_metadata);
+        }
+
+        let hashes: Vec<String> = updated_metadata
+            .iter()
+            .map(|metadata| metadata.hash.clone())
+            .collect();
+        let before = change_log::take_snapshots(conn, &hashes, false)?;
+
+        for metadata in updated_metadata.iter_mut() {
+            metadata.timestamp_modified = timestamp_modified.max(metadata.timestamp_modified + 1);
+            diesel::update(&*metadata)
+                .set(metadata.clone())
+                .execute(conn)?;
+        }
+
+        let after = change_log::take_snapshots(conn, &hashes, false)?;
+        change_log::record_changes(conn, ChangeOperation::Update, before, after)?;
+
+        Ok(updated_metadata)
+    })?;
+
+    searcher::update_metadata(&updated_metadata)?;
+
+    Ok(updated_metadata)
+}
@@ -12,6 +12,19 @@ use crate::{
 pub struct Preferences {
     pub recent_searches: Vec<String>,
     pub show_file_extensions: bool,
+    #[serde(default = "default_thumbnail_cache_limit_mb")]
+    pub thumbnail_cache_limit_mb: u32,
+    /// Trashed items older than this are purged for good, 0 keeps them forever
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+}
+
+fn default_thumbnail_cache_limit_mb() -> u32 {
+    1024
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
 }
 
 pub fn create_if_not_exists() -> Result<()> {
@@ -23,6 +36,8 @@ pub fn create_if_not_exists() -> Result<()> {
     let preferences = Preferences {
         recent_searches: Vec::new(),
         show_file_extensions: false,
+        thumbnail_cache_limit_mb: default_thumbnail_cache_limit_mb(),
+        trash_retention_days: default_trash_retention_days(),
     };
 
     let mut file = File::create(preferences_path)?;
@@ -1,3 +1,75 @@
+table! {
+    change_log (id) {
+        id -> Integer,
+        batchId -> Integer,
+        hash -> Text,
+        operation -> Text,
+        before -> Nullable<Text>,
+        after -> Nullable<Text>,
+        timestamp -> Integer,
+        undone -> Bool,
+    }
+}
+
+table! {
+    collection (id) {
+        id -> Integer,
+        name -> Text,
+        description -> Nullable<Text>,
+        timestampCreated -> Integer,
+    }
+}
+
+table! {
+    collection_item (collectionId, hash) {
+        collectionId -> Integer,
+        hash -> Text,
+        position -> Integer,
+    }
+}
+
+table! {
+    content_text (hash) {
+        hash -> Text,
+        body -> Text,
+    }
+}
+
+table! {
+    custom_field (id) {
+        id -> Integer,
+        name -> Text,
+        fieldType -> Text,
+        options -> Nullable<Text>,
+    }
+}
+
+table! {
+    custom_field_value (hash, fieldId) {
+        hash -> Text,
+        fieldId -> Integer,
+        value -> Text,
+    }
+}
+
+table! {
+    item_link (id) {
+        id -> Integer,
+        sourceHash -> Text,
+        targetHash -> Text,
+        relation -> Text,
+        timestampCreated -> Integer,
+    }
+}
+
+table! {
+    media_info (hash, key) {
+        hash -> Text,
+        key -> Text,
+        value -> Text,
+    }
+}
+
 table! {
     metadata (hash) {
         hash -> Text,
@@ -13,6 +85,19 @@ table! {
         width -> Nullable<Integer>,
         height -> Nullable<Integer>,
         duration -> Nullable<Integer>,
+        rating -> Integer,
+        favorite -> Bool,
+        timestampTrashed -> Nullable<Integer>,
+    }
+}
+
+table! {
+    note (id) {
+        id -> Integer,
+        hash -> Text,
+        body -> Text,
+        timestampCreated -> Integer,
+        timestampModified -> Integer,
     }
 }
 
@@ -23,6 +108,17 @@ table! {
     }
 }
 
+table! {
+    saved_search (id) {
+        id -> Integer,
+        name -> Text,
+        query -> Text,
+        filter -> Text,
+        itemCount -> Integer,
+        timestampCreated -> Integer,
+    }
+}
+
 table! {
     smart_folder (path) {
         name -> Text,
@@ -31,4 +127,30 @@ table! {
     }
 }
 
-allow_tables_to_appear_in_same_query!(metadata, preferences, smart_folder,);
+table! {
+    tag_rule (id) {
+        id -> Integer,
+        name -> Text,
+        condition -> Text,
+        pattern -> Text,
+        tags -> Nullable<Text>,
+        enabled -> Bool,
+    }
+}
+
+allow_tables_to_appear_in_same_query!(
+    change_log,
+    collection,
+    collection_item,
+    content_text,
+    custom_field,
+    custom_field_value,
+    item_link,
+    media_info,
+    metadata,
+    note,
+    preferences,
+    saved_search,
+    smart_folder,
+    tag_rule,
+);
@@ -1,18 +1,34 @@
 use crate::{
     directory::{get_dir_path, Dir},
     foc_error::Result,
-    models::metadata::{self, get_metadata_by_hash, Metadata},
+    markdown::markdown_to_text,
+    models::{
+        collection, content_text,
+        custom_field::{self, CustomFieldFilter},
+        media_info,
+        metadata::{self, get_metadata_by_hash, Metadata, Status},
+        note,
+    },
 };
 use once_cell::sync::OnceCell;
-use std::{collections::VecDeque, sync::Mutex};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
 use tantivy::directory::MmapDirectory;
 use tantivy::{
-    collector::TopDocs,
-    query::{BooleanQuery, Occur, QueryClone},
+    collector::{Count, TopDocs},
+    query::{AllQuery, BooleanQuery, Occur, Query, QueryClone, RangeQuery, TermQuery},
     IndexReader, IndexWriter,
 };
 use tantivy::{query::FuzzyTermQuery, schema::*};
 use tantivy::{Index, ReloadPolicy};
+use ts_rs::TS;
+
+/// Every pass over a text search allows one more edit per word than the one before
+const FUZZY_ITERATIONS: u8 = 3;
 
 static READER_INSTANCE: OnceCell<IndexReader> = OnceCell::new();
 static WRITER_INSTANCE: OnceCell<Mutex<IndexWriter>> = OnceCell::new();
@@ -24,20 +40,40 @@ fn get_index() -> Result<Index> {
     Ok(index)
 }
 
+fn build_schema() -> Schema {
+    let mut schema_builder = Schema::builder();
+    schema_builder.add_text_field("hash", STRING | STORED);
+    schema_builder.add_text_field("name", TEXT);
+    schema_builder.add_text_field("tags", TEXT);
+    schema_builder.add_text_field("notes", TEXT);
+    schema_builder.add_text_field("body", TEXT);
+    schema_builder.add_text_field("media_info", TEXT);
+    schema_builder.add_text_field("custom", TEXT);
+    schema_builder.add_u64_field("rating", INDEXED | FAST);
+    schema_builder.add_u64_field("favorite", INDEXED);
+    schema_builder.build()
+}
+
 pub async fn init_reader() -> Result<()> {
     let index_path = get_dir_path(Dir::Index)?;
     let mmap_dir = MmapDirectory::open(&index_path).unwrap();
+    let schema = build_schema();
+    let mut needs_reindex = false;
 
     let index: Index = if Index::exists(&mmap_dir)? {
-        Index::open(mmap_dir)?
+        let existing_index = Index::open(mmap_dir)?;
+        if existing_index.schema() == schema {
+            existing_index
+        } else {
+            // The index was created by an older version with different fields,
+            // so we throw it away and rebuild it from the database
+            drop(existing_index);
+            std::fs::remove_dir_all(&index_path)?;
+            std::fs::create_dir_all(&index_path)?;
+            needs_reindex = true;
+            Index::create_in_dir(&index_path, schema)?
+        }
     } else {
-        let mut schema_builder = Schema::builder();
-        schema_builder.add_text_field("hash", STRING | STORED);
-        schema_builder.add_text_field("name", TEXT);
-        schema_builder.add_text_field("tags", TEXT);
-        schema_builder.add_text_field("notes", TEXT);
-        let schema = schema_builder.build();
-
         Index::create_in_dir(&index_path, schema)?
     };
 
@@ -51,34 +87,82 @@ pub async fn init_reader() -> Result<()> {
     let _ = READER_INSTANCE.set(reader);
     let _ = WRITER_INSTANCE.set(Mutex::new(writer));
 
+    if needs_reindex {
+        let all_metadata = metadata::get_all_metadata().await?;
+        index_metadata(&all_metadata)?;
+    }
+
     Ok(())
 }
 
+fn create_document(
+    schema: &Schema,
+    metadata: &Metadata,
+    note_bodies: &HashMap<String, Vec<String>>,
+) -> Result<Document> {
+    let hash_field = schema.get_field("hash").unwrap();
+    let name_field = schema.get_field("name").unwrap();
+    let tags_field = schema.get_field("tags").unwrap();
+    let notes_field = schema.get_field("notes").unwrap();
+    let body_field = schema.get_field("body").unwrap();
+    let media_info_field = schema.get_field("media_info").unwrap();
+    let custom_values_field = schema.get_field("custom").unwrap();
+    let rating_field = schema.get_field("rating").unwrap();
+    let favorite_field = schema.get_field("favorite").unwrap();
+
+    let mut doc = Document::new();
+    doc.add_text(hash_field, &metadata.hash);
+    doc.add_text(name_field, &metadata.name);
+    doc.add_u64(rating_field, metadata.rating.max(0) as u64);
+    doc.add_u64(favorite_field, metadata.favorite as u64);
+    if let Some(notes) = &metadata.notes {
+        doc.add_text(notes_field, markdown_to_text(notes));
+    }
+    for note in note_bodies.get(&metadata.hash).into_iter().flatten() {
+        doc.add_text(notes_field, markdown_to_text(note));
+    }
+    if let Some(tags) = &metadata.tags {
+        for tag in tags.iter() {
+            doc.add_text(tags_field, &tag);
+        }
+    }
+    if let Some(body) = content_text::get_content_text(&metadata.hash)? {
+        doc.add_text(body_field, &body);
+    }
+    for media_info in media_info::get_media_info_by_hash(&metadata.hash)? {
+        doc.add_text(media_info_field, &media_info.value);
+    }
+    for custom_value in custom_field::get_custom_field_values_by_hash(&metadata.hash)? {
+        doc.add_text(custom_values_field, &custom_value.value);
+    }
+
+    Ok(doc)
+}
+
+fn load_note_bodies(metadata: &[Metadata]) -> Result<HashMap<String, Vec<String>>> {
+    let hashes: Vec<String> = metadata
+        .iter()
+        .map(|metadata| metadata.hash.clone())
+        .collect();
+    note::get_note_bodies_by_hashes(&hashes)
+}
+
+/// Trashed items stay in the database until they're purged but never show up in search
+fn is_trashed(metadata: &Metadata) -> bool {
+    matches!(metadata.status, Status::Trashed)
+}
+
 pub fn index_metadata(metadata: &[Metadata]) -> Result<()> {
     let index = get_index()?;
     let reader = READER_INSTANCE.get().unwrap();
     let mutex_writer = WRITER_INSTANCE.get().unwrap();
     let mut writer = mutex_writer.lock().unwrap();
 
-    let hash_field = index.schema().get_field("hash").unwrap();
-    let name_field = index.schema().get_field("name").unwrap();
-    let tags_field = index.schema().get_field("tags").unwrap();
-    let notes_field = index.schema().get_field("notes").unwrap();
-
-    for metadata in metadata {
-        let mut doc = Document::new();
-
-        doc.add_text(hash_field, &metadata.hash);
-        doc.add_text(name_field, &metadata.name);
-        if let Some(notes) = &metadata.notes {
-            doc.add_text(notes_field, &notes);
-        }
-        if let Some(tags) = &metadata.tags {
-            for tag in tags.iter() {
-                doc.add_text(tags_field, &tag);
-            }
-        }
+    let schema = index.schema();
+    let note_bodies = load_note_bodies(metadata)?;
 
+    for metadata in metadata.iter().filter(|metadata| !is_trashed(metadata)) {
+        let doc = create_document(&schema, metadata, &note_bodies)?;
         writer.add_document(doc)?;
         println!("Indexed: {}", metadata.name);
     }
@@ -107,31 +191,25 @@ pub fn delete_metadata(hashes: &Vec<String>) -> Result<()> {
     Ok(())
 }
 
-pub fn update_metadata(metadata: &Metadata) -> Result<()> {
+/// Replaces the documents of all given items, committing to the index only once
+pub fn update_metadata(metadata: &[Metadata]) -> Result<()> {
     let index = get_index()?;
     let reader = READER_INSTANCE.get().unwrap();
     let mutex_writer = WRITER_INSTANCE.get().unwrap();
     let mut writer = mutex_writer.lock().unwrap();
 
-    let hash_field = index.schema().get_field("hash").unwrap();
-    let name_field = index.schema().get_field("name").unwrap();
-    let tags_field = index.schema().get_field("tags").unwrap();
-    let notes_field = index.schema().get_field("notes").unwrap();
+    let schema = index.schema();
+    let hash_field = schema.get_field("hash").unwrap();
+    let note_bodies = load_note_bodies(metadata)?;
 
-    let metadata_hash = Term::from_field_text(hash_field, &metadata.hash);
-    let mut doc = Document::new();
-    doc.add_text(hash_field, &metadata.hash);
-    doc.add_text(name_field, &metadata.name);
-    if let Some(notes) = &metadata.notes {
-        doc.add_text(notes_field, &notes);
-    }
-    if let Some(tags) = &metadata.tags {
-        for tag in tags.iter() {
-            doc.add_text(tags_field, &tag);
+    for metadata in metadata {
+        let metadata_hash = Term::from_field_text(hash_field, &metadata.hash);
+        writer.delete_term(metadata_hash);
+        if !is_trashed(metadata) {
+            let doc = create_document(&schema, metadata, &note_bodies)?;
+            writer.add_document(doc)?;
         }
     }
-    writer.delete_term(metadata_hash);
-    writer.add_document(doc)?;
 
     writer.commit()?;
     reader.reload()?;
@@ -139,50 +217,228 @@ pub fn update_metadata(metadata: &Metadata) -> Result<()> {
     Ok(())
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum SortBy {
+    Relevance,
+    Rating,
+    Name,
+    DateCreated,
+    DateModified,
+}
+
+impl Default for SortBy {
+    fn default() -> Self {
+        SortBy::Relevance
+    }
+}
+
+/// Everything besides the query text that narrows down or orders search results
+#[derive(Serialize, Deserialize, Default, Clone, Debug, TS)]
+#[ts(export)]
+pub struct SearchFilter {
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldFilter>,
+    pub min_rating: Option<i32>,
+    #[serde(default)]
+    pub favorites_only: bool,
+    #[serde(default)]
+    pub sort_by: SortBy,
+    /// Only search within this collection
+    pub collection_id: Option<i32>,
+}
+
+/// Matches only the documents of the given items
+fn build_hash_filter(hash_field: Field, hashes: &HashSet<String>) -> Box<dyn Query> {
+    let by_hash = hashes
+        .iter()
+        .map(|hash| {
+            let term = Term::from_field_text(hash_field, hash);
+            let query: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+            (Occur::Should, query)
+        })
+        .collect();
+
+    Box::new(BooleanQuery::new(by_hash))
+}
+
+/// Every returned query has to match for a document to be part of the results
+fn build_filter_queries(
+    schema: &Schema,
+    filter: &SearchFilter,
+) -> Result<Vec<(Occur, Box<dyn Query>)>> {
+    let hash_field = schema.get_field("hash").unwrap();
+    let rating_field = schema.get_field("rating").unwrap();
+    let favorite_field = schema.get_field("favorite").unwrap();
+    let mut queries: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+
+    if let Some(hashes) = custom_field::find_hashes_matching(&filter.custom_fields)? {
+        queries.push((Occur::Must, build_hash_filter(hash_field, &hashes)));
+    }
+    if let Some(collection_id) = filter.collection_id {
+        let hashes: HashSet<String> = collection::get_collection_hashes(collection_id)?
+            .into_iter()
+            .collect();
+        queries.push((Occur::Must, build_hash_filter(hash_field, &hashes)));
+    }
+    if let Some(min_rating) = filter.min_rating.filter(|min_rating| *min_rating > 0) {
+        let range = min_rating as u64..(metadata::MAX_RATING as u64 + 1);
+        queries.push((
+            Occur::Must,
+            Box::new(RangeQuery::new_u64(rating_field, range)),
+        ));
+    }
+    if filter.favorites_only {
+        let term = Term::from_field_u64(favorite_field, 1);
+        queries.push((
+            Occur::Must,
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+        ));
+    }
+
+    Ok(queries)
+}
+
+/// Sorting is stable, so items that compare equal keep their relevance order
+fn sort_metadata(found_metadata: &mut [Metadata], sort_by: &SortBy) {
+    match sort_by {
+        SortBy::Relevance => {}
+        SortBy::Rating => found_metadata.sort_by_key(|metadata| Reverse(metadata.rating)),
+        SortBy::Name => found_metadata.sort_by_key(|metadata| metadata.name.to_lowercase()),
+        SortBy::DateCreated => {
+            found_metadata.sort_by_key(|metadata| Reverse(metadata.timestamp_created))
+        }
+        SortBy::DateModified => {
+            found_metadata.sort_by_key(|metadata| Reverse(metadata.timestamp_modified))
+        }
+    }
+}
+
+fn get_searchable_fields(schema: &Schema) -> [Field; 6] {
+    ["name", "tags", "notes", "body", "media_info", "custom"]
+        .map(|field_name| schema.get_field(field_name).unwrap())
+}
+
+/// Documents where any of the words is within `distance` edits of the start of a word in any
+/// searchable field, and that match all the filters
+fn build_text_query(
+    searchable_fields: &[Field],
+    query_no_extra_spaces: &str,
+    distance: u8,
+    filter_queries: &[(Occur, Box<dyn Query>)],
+) -> BooleanQuery {
+    let mut sub_queries = Vec::new();
+    for word in query_no_extra_spaces.split(' ') {
+        let by_any_field = BooleanQuery::new(
+            searchable_fields
+                .iter()
+                .map(|field| {
+                    let term = Term::from_field_text(*field, word);
+                    let query = FuzzyTermQuery::new_prefix(term, distance, true);
+                    (Occur::Should, query.box_clone())
+                })
+                .collect(),
+        );
+        sub_queries.push((Occur::Should, by_any_field.box_clone()))
+    }
+
+    let mut search_query: Vec<(Occur, Box<dyn Query>)> =
+        vec![(Occur::Must, Box::new(BooleanQuery::new(sub_queries)))];
+    for (occur, query) in filter_queries.iter() {
+        search_query.push((*occur, query.box_clone()));
+    }
+
+    BooleanQuery::new(search_query)
+}
+
+/// How many items a search finds in total, `search_content` only returns the best matches
+pub fn count_content(query: &str, filter: &SearchFilter) -> Result<usize> {
+    let reader = READER_INSTANCE.get().unwrap();
+    let index = get_index()?;
+    let schema = index.schema();
+
+    let filter_queries = build_filter_queries(&schema, filter)?;
+    let searcher = reader.searcher();
+    let query_no_extra_spaces = remove_all_extra_whitespace(query);
+
+    let count = if query_no_extra_spaces.is_empty() && !filter_queries.is_empty() {
+        let mut filter_only_query = filter_queries;
+        filter_only_query.push((Occur::Must, Box::new(AllQuery)));
+        searcher.search(&BooleanQuery::new(filter_only_query), &Count)?
+    } else {
+        // The most forgiving pass matches everything the stricter ones do
+        let search_query = build_text_query(
+            &get_searchable_fields(&schema),
+            &query_no_extra_spaces,
+            FUZZY_ITERATIONS - 1,
+            &filter_queries,
+        );
+        searcher.search(&search_query, &Count)?
+    };
+
+    Ok(count)
+}
+
 #[tauri::command]
-pub async fn search_content(query: &str) -> Result<Vec<Metadata>> {
+pub async fn search_content(query: &str, filter: Option<SearchFilter>) -> Result<Vec<Metadata>> {
     let reader = READER_INSTANCE.get().unwrap();
     let index = get_index()?;
     let schema = index.schema();
+    let filter = filter.unwrap_or_default();
 
     let hash_field = schema.get_field("hash").unwrap();
-    let name_field = schema.get_field("name").unwrap();
-    let tags_field = schema.get_field("tags").unwrap();
-    let notes_field = schema.get_field("notes").unwrap();
+    let searchable_fields = get_searchable_fields(&schema);
 
+    let filter_queries = build_filter_queries(&schema, &filter)?;
     let searcher = reader.searcher();
 
     // TODO: add searching by smart folder or all search
     // TODO: search for regex with RegexQuery (according to chillfish)
     // TODO: we can make these settings into options from the Preferences menu
     let desired_amount_of_docs: u8 = 30;
-    let number_of_fuzzy_iterations: u8 = 3;
     let mut previous_doc_adresses = VecDeque::with_capacity(desired_amount_of_docs as usize);
     let query_no_extra_spaces = remove_all_extra_whitespace(query);
 
-    println!("=============================================");
+    // Without any words to search for, the filters alone decide what is shown
+    if query_no_extra_spaces.is_empty() && !filter_queries.is_empty() {
+        let mut filter_only_query = filter_queries;
+        filter_only_query.push((Occur::Must, Box::new(AllQuery)));
+        let limit = (searcher.num_docs() as usize).max(1);
+
+        let mut found_metadata = Vec::new();
+        for (.., doc_address) in searcher.search(
+            &BooleanQuery::new(filter_only_query),
+            &TopDocs::with_limit(limit),
+        )? {
+            let doc = searcher.doc(doc_address)?;
+            let file_hash = doc.get_first(hash_field).unwrap().as_text().unwrap();
+            found_metadata.push(metadata::get_metadata_by_hash(file_hash).await?);
+        }
 
-    for search_iteration in 0..number_of_fuzzy_iterations {
-        let mut sub_queries = Vec::new();
-        for word in query_no_extra_spaces.split(' ') {
-            let name_term = Term::from_field_text(name_field, word);
-            let tags_term = Term::from_field_text(tags_field, word);
-            let notes_term = Term::from_field_text(notes_field, word);
+        // Browsing a collection shows it in the order it was arranged in
+        match filter.collection_id {
+            Some(collection_id) if filter.sort_by == SortBy::Relevance => {
+                let order = collection::get_collection_hashes(collection_id)?;
+                collection::sort_by_collection_order(&mut found_metadata, &order);
+            }
+            _ => found_metadata.sort_by(|a, b| a.name.cmp(&b.name)),
+        }
+        sort_metadata(&mut found_metadata, &filter.sort_by);
+        return Ok(found_metadata);
+    }
 
-            let by_name = FuzzyTermQuery::new_prefix(name_term, search_iteration, true);
-            let by_tag = FuzzyTermQuery::new_prefix(tags_term, search_iteration, true);
-            let by_notes = FuzzyTermQuery::new_prefix(notes_term, search_iteration, true);
+    println!("=============================================");
 
-            let by_name_tag_notes = BooleanQuery::new(vec![
-                (Occur::Should, by_name.box_clone()),
-                (Occur::Should, by_tag.box_clone()),
-                (Occur::Should, by_notes.box_clone()),
-            ]);
-            sub_queries.push((Occur::Should, by_name_tag_notes.box_clone()))
-        }
+    for search_iteration in 0..FUZZY_ITERATIONS {
+        let search_query = build_text_query(
+            &searchable_fields,
+            &query_no_extra_spaces,
+            search_iteration,
+            &filter_queries,
+        );
 
         let found_documents = searcher.search(
-            &BooleanQuery::new(sub_queries),
+            &search_query,
             &(TopDocs::with_limit(desired_amount_of_docs.into())),
         )?;
 
@@ -208,6 +464,8 @@ pub async fn search_content(query: &str) -> Result<Vec<Metadata>> {
         found_metadata.push(metadata);
     }
 
+    sort_metadata(&mut found_metadata, &filter.sort_by);
+
     Ok(found_metadata)
 }
 
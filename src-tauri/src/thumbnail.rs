@@ -0,0 +1,573 @@
+use crate::{
+    audio::{extract_cover_art, render_waveform},
+    directory::{get_dir_path, Dir},
+    file_utils::get_exif_orientation,
+    foc_error::Result,
+    models::{
+        change_log,
+        metadata::{self, ContentType, Metadata},
+    },
+    preferences::get_preferences,
+};
+use filetime::FileTime;
+use image::{
+    codecs::gif::{GifDecoder, GifEncoder, Repeat},
+    AnimationDecoder, DynamicImage, Frame, ImageFormat,
+};
+use mime::Mime;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, ErrorKind, Seek},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+use thumbnailer::create_thumbnails;
+use ts_rs::TS;
+
+// Animated previews are kept small since every single frame ends up in the file
+const ANIMATED_PREVIEW_MAX_SIZE: u32 = 256;
+const ANIMATED_PREVIEW_MAX_FRAMES: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, TS)]
+#[ts(export)]
+pub enum ThumbnailSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 3] = [
+        ThumbnailSize::Small,
+        ThumbnailSize::Medium,
+        ThumbnailSize::Large,
+    ];
+
+    fn to_thumbnailer_size(self) -> thumbnailer::ThumbnailSize {
+        match self {
+            ThumbnailSize::Small => thumbnailer::ThumbnailSize::Small,
+            ThumbnailSize::Medium => thumbnailer::ThumbnailSize::Medium,
+            ThumbnailSize::Large => thumbnailer::ThumbnailSize::Large,
+        }
+    }
+
+    fn file_name(self, hash: &str) -> String {
+        match self {
+            ThumbnailSize::Small => format!("{}_small.png", hash),
+            ThumbnailSize::Medium => format!("{}_medium.png", hash),
+            // Large thumbnails keep the name from before there were multiple sizes
+            ThumbnailSize::Large => format!("{}.png", hash),
+        }
+    }
+}
+
+pub fn thumbnail_path(hash: &str, size: ThumbnailSize) -> Result<PathBuf> {
+    Ok(get_dir_path(Dir::Thumbnails)?.join(size.file_name(hash)))
+}
+
+pub fn preview_path(hash: &str) -> Result<PathBuf> {
+    Ok(get_dir_path(Dir::Thumbnails)?.join(format!("{}_preview.gif", hash)))
+}
+
+/// Every file in the thumbnail dir starts with the hash of the content it belongs to
+fn hash_from_thumbnail_name(file_name: &str) -> &str {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    stem.split('_').next().unwrap_or(stem)
+}
+
+pub fn generate_thumbnail_from_file(metadata: &Metadata, file: &File) -> Result<()> {
+    match metadata.content_type {
+        ContentType::Image => generate_image_thumbnail(metadata, file),
+        ContentType::Gif => {
+            generate_image_thumbnail(metadata, file)?;
+            if let Err(error) = generate_animated_preview(metadata, file) {
+                println!("Couldn't generate preview for {}: {}", metadata.path, error);
+            }
+            Ok(())
+        }
+        ContentType::Video => generate_video_thumbnail(metadata, file),
+        ContentType::Audio => generate_audio_thumbnail(metadata),
+        _ => Ok(()),
+    }
+}
+
+fn generate_image_thumbnail(metadata: &Metadata, file: &File) -> Result<()> {
+    let image_format = metadata
+        .extension
+        .as_deref()
+        .and_then(ImageFormat::from_extension);
+
+    let orientation = get_exif_orientation(&metadata.path).unwrap_or(1);
+
+    let mut reader = BufReader::new(file);
+    reader.rewind()?;
+
+    let image_type = match image_format {
+        Some(ImageFormat::Png) => mime::IMAGE_PNG,
+        Some(ImageFormat::Jpeg) => mime::IMAGE_JPEG,
+        Some(ImageFormat::Bmp) => mime::IMAGE_BMP,
+        Some(image_format) => {
+            // Everything else is decoded by us and handed over as a PNG, for
+            // animated formats this also means we only get the first frame
+            let image = match image::load(reader, image_format) {
+                Ok(image) => image,
+                Err(error) => {
+                    println!(
+                        "Couldn't generate thumbnail for {}: {}",
+                        metadata.path, error
+                    );
+                    return Ok(());
+                }
+            };
+
+            let image = apply_orientation(image, orientation);
+            create_thumbnail_from_image(metadata.hash.as_str(), &image)?;
+            return Ok(());
+        }
+        None => {
+            println!("No thumbnail for {}, unknown image type", metadata.path);
+            return Ok(());
+        }
+    };
+
+    create_oriented_thumbnail(metadata.hash.as_str(), reader, image_type, orientation)?;
+
+    Ok(())
+}
+
+/// Turns the stored pixels into what the EXIF orientation says the image should look like
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+fn generate_animated_preview(metadata: &Metadata, file: &File) -> Result<()> {
+    let mut reader = BufReader::new(file);
+    reader.rewind()?;
+
+    let frames = GifDecoder::new(reader)?
+        .into_frames()
+        .take(ANIMATED_PREVIEW_MAX_FRAMES)
+        .map(|frame| -> Result<Frame> {
+            let frame = frame?;
+            let (width, height) = fit_within(
+                frame.buffer().width(),
+                frame.buffer().height(),
+                ANIMATED_PREVIEW_MAX_SIZE,
+            );
+            let buffer = image::imageops::thumbnail(frame.buffer(), width, height);
+            Ok(Frame::from_parts(buffer, 0, 0, frame.delay()))
+        })
+        .collect::<Result<Vec<Frame>>>()?;
+
+    // A single frame gif already looks exactly like its thumbnail
+    if frames.len() < 2 {
+        return Ok(());
+    }
+
+    let mut encoder = GifEncoder::new(File::create(preview_path(&metadata.hash)?)?);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(frames)?;
+
+    Ok(())
+}
+
+fn fit_within(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    if width <= max_size && height <= max_size {
+        return (width, height);
+    }
+
+    let ratio = f64::from(max_size) / f64::from(width.max(height));
+    let fitted_width = (f64::from(width) * ratio).round() as u32;
+    let fitted_height = (f64::from(height) * ratio).round() as u32;
+    (fitted_width.max(1), fitted_height.max(1))
+}
+
+fn generate_audio_thumbnail(metadata: &Metadata) -> Result<()> {
+    let extension = metadata.extension.as_deref();
+
+    let cover_art = match extract_cover_art(&metadata.path, extension) {
+        Ok(cover_art) => cover_art.and_then(|data| image::load_from_memory(&data).ok()),
+        Err(error) => {
+            println!("Couldn't read cover art of {}: {}", metadata.path, error);
+            None
+        }
+    };
+
+    // Without any artwork the waveform at least tells tracks apart
+    let image = match cover_art {
+        Some(cover_art) => cover_art,
+        None => match render_waveform(&metadata.path, extension) {
+            Ok(Some(waveform)) => DynamicImage::ImageRgba8(waveform),
+            Ok(None) => return Ok(()),
+            Err(error) => {
+                println!("Couldn't render waveform of {}: {}", metadata.path, error);
+                return Ok(());
+            }
+        },
+    };
+
+    create_thumbnail_from_image(metadata.hash.as_str(), &image)?;
+
+    Ok(())
+}
+
+fn generate_video_thumbnail(metadata: &Metadata, file: &File) -> Result<()> {
+    let video_type = match video_mime_from_extension(metadata.extension.as_deref()) {
+        Some(video_type) => video_type,
+        None => {
+            println!("No thumbnail for {}, unknown video type", metadata.path);
+            return Ok(());
+        }
+    };
+
+    // Poster frames are grabbed through ffmpeg, which might be missing or not know the codec.
+    // A video without a thumbnail is still perfectly usable so this shouldn't fail the import.
+    let reader = BufReader::new(file);
+    if let Err(error) = create_thumbnail(metadata.hash.as_str(), reader, video_type) {
+        println!(
+            "Couldn't generate thumbnail for {}: {}",
+            metadata.path, error
+        );
+    }
+
+    Ok(())
+}
+
+fn video_mime_from_extension(extension: Option<&str>) -> Option<Mime> {
+    let mime_type = match extension?.to_lowercase().as_str() {
+        "mp4" => "video/mp4",
+        "m4v" => "video/x-m4v",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "avi" => "video/x-msvideo",
+        "wmv" => "video/x-ms-wmv",
+        "mpg" | "mpeg" => "video/mpeg",
+        "flv" => "video/x-flv",
+        _ => return None,
+    };
+
+    mime_type.parse().ok()
+}
+
+fn create_thumbnail_from_image(name: &str, image: &DynamicImage) -> Result<(i32, i32)> {
+    let mut png_buf = Cursor::new(Vec::new());
+    image.write_to(&mut png_buf, ImageFormat::Png)?;
+    png_buf.rewind()?;
+
+    create_thumbnail(name, png_buf, mime::IMAGE_PNG)
+}
+
+/// Creates the thumbnail in every size and returns the dimensions of the large one
+pub fn create_thumbnail<R>(name: &str, reader: R, image_type: Mime) -> Result<(i32, i32)>
+where
+    R: BufRead + Seek,
+{
+    create_oriented_thumbnail(name, reader, image_type, 1)
+}
+
+fn create_oriented_thumbnail<R>(
+    name: &str,
+    reader: R,
+    image_type: Mime,
+    orientation: u32,
+) -> Result<(i32, i32)>
+where
+    R: BufRead + Seek,
+{
+    let sizes = ThumbnailSize::ALL.map(ThumbnailSize::to_thumbnailer_size);
+    let thumbnails = create_thumbnails(reader, image_type, sizes)?;
+    let mut large_dimensions = (0, 0);
+
+    for (size, thumbnail_raw) in ThumbnailSize::ALL.into_iter().zip(thumbnails) {
+        let mut thumbnail_buf = Cursor::new(Vec::new());
+        thumbnail_raw.write_png(&mut thumbnail_buf)?;
+
+        let thumbnail =
+            image::load_from_memory_with_format(thumbnail_buf.get_ref(), image::ImageFormat::Png)
+                .unwrap();
+        let thumbnail = apply_orientation(thumbnail, orientation);
+        thumbnail.save(thumbnail_path(name, size)?)?;
+
+        if let ThumbnailSize::Large = size {
+            large_dimensions = (thumbnail.width() as i32, thumbnail.height() as i32);
+        }
+    }
+
+    Ok(large_dimensions)
+}
+
+/// Items whose thumbnails couldn't be generated in this session, showing them in the gallery
+/// doesn't run the decoders again every time, only an explicit regeneration does
+static FAILED_THUMBNAILS: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+fn has_thumbnail(content_type: &ContentType) -> bool {
+    matches!(
+        content_type,
+        ContentType::Image | ContentType::Gif | ContentType::Video | ContentType::Audio
+    )
+}
+
+/// Every file generated for the item, which is each thumbnail size plus the preview for GIFs
+fn generated_paths(metadata: &Metadata) -> Result<Vec<PathBuf>> {
+    let mut paths = ThumbnailSize::ALL
+        .into_iter()
+        .map(|size| thumbnail_path(&metadata.hash, size))
+        .collect::<Result<Vec<_>>>()?;
+    if let ContentType::Gif = metadata.content_type {
+        paths.push(preview_path(&metadata.hash)?);
+    }
+
+    Ok(paths)
+}
+
+/// A thumbnail needs to be regenerated if any file is missing or older than the file itself
+fn needs_regeneration(metadata: &Metadata) -> Result<bool> {
+    let file_modified = std::fs::metadata(&metadata.path)?.modified()?;
+
+    for path in generated_paths(metadata)? {
+        match std::fs::metadata(path) {
+            Ok(thumbnail) if thumbnail.modified()? >= file_modified => continue,
+            _ => return Ok(true),
+        }
+    }
+
+    Ok(false)
+}
+
+fn has_all_files(metadata: &Metadata) -> Result<bool> {
+    Ok(generated_paths(metadata)?.iter().all(|path| path.exists()))
+}
+
+#[tauri::command]
+pub async fn get_thumbnail_path(hash: String, size: ThumbnailSize) -> Result<Option<String>> {
+    if let Some(path) = find_thumbnail(&hash, size)? {
+        return Ok(Some(path));
+    }
+    if FAILED_THUMBNAILS.lock().unwrap().contains(&hash) {
+        return Ok(None);
+    }
+
+    // The thumbnail was evicted from the cache or the cache dir got wiped
+    let all_metadata = metadata::get_metadata_by_hashes(std::slice::from_ref(&hash)).await?;
+    regenerate(all_metadata)?;
+    find_thumbnail(&hash, size)
+}
+
+/// Small animated version of a GIF for the gallery, there is none for any other content
+#[tauri::command]
+pub async fn get_preview_path(hash: String) -> Result<Option<String>> {
+    let path = preview_path(&hash)?;
+    if !path.exists() {
+        if FAILED_THUMBNAILS.lock().unwrap().contains(&hash) {
+            return Ok(None);
+        }
+
+        let all_metadata = metadata::get_metadata_by_hashes(std::slice::from_ref(&hash)).await?;
+        regenerate(all_metadata)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+    }
+
+    let _ = filetime::set_file_atime(&path, FileTime::now());
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+fn find_thumbnail(hash: &str, size: ThumbnailSize) -> Result<Option<String>> {
+    // Fall back to any other size while the requested one hasn't been regenerated yet
+    let fallback_sizes = ThumbnailSize::ALL
+        .into_iter()
+        .filter(|other| *other != size);
+
+    for size in std::iter::once(size).chain(fallback_sizes) {
+        let path = thumbnail_path(hash, size)?;
+        if path.exists() {
+            // The access time is what the cache eviction goes by
+            let _ = filetime::set_file_atime(&path, FileTime::now());
+            return Ok(Some(path.to_string_lossy().into_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Regenerates missing or stale thumbnails for the given hashes, or the whole library
+/// if none are given, and returns how many items got new thumbnails.
+/// Items that failed before are tried again, unknown hashes are skipped.
+#[tauri::command]
+pub async fn regenerate_thumbnails(hashes: Option<Vec<String>>) -> Result<u32> {
+    let all_metadata = match hashes {
+        Some(hashes) => metadata::get_metadata_by_hashes(&hashes).await?,
+        None => metadata::get_all_metadata().await?,
+    };
+
+    regenerate(all_metadata)
+}
+
+fn regenerate(all_metadata: Vec<Metadata>) -> Result<u32> {
+    let mut regenerated = 0;
+    for metadata in all_metadata {
+        // Links get their thumbnail from the extension's screenshot, there's no file to redo it from
+        if !has_thumbnail(&metadata.content_type) || !Path::new(&metadata.path).exists() {
+            continue;
+        }
+
+        if !needs_regeneration(&metadata)? {
+            continue;
+        }
+
+        // Decoders that fail only log it, so what counts is whether the files are there afterwards
+        let file = File::open(&metadata.path)?;
+        let succeeded = match generate_thumbnail_from_file(&metadata, &file) {
+            Ok(_) if has_all_files(&metadata)? => true,
+            Ok(_) => {
+                println!("Couldn't regenerate thumbnail for {}", metadata.path);
+                false
+            }
+            Err(error) => {
+                println!(
+                    "Couldn't regenerate thumbnail for {}: {}",
+                    metadata.path, error
+                );
+                false
+            }
+        };
+
+        let mut failed_thumbnails = FAILED_THUMBNAILS.lock().unwrap();
+        if succeeded {
+            failed_thumbnails.remove(&metadata.hash);
+            regenerated += 1;
+        } else {
+            failed_thumbnails.insert(metadata.hash);
+        }
+    }
+
+    Ok(regenerated)
+}
+
+pub fn delete_thumbnails(hashes: &[String]) -> Result<()> {
+    for hash in hashes {
+        for size in ThumbnailSize::ALL {
+            remove_if_exists(&thumbnail_path(hash, size)?)?;
+        }
+        remove_if_exists(&preview_path(hash)?)?;
+    }
+
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(_) => Ok(()),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+struct CachedThumbnail {
+    path: PathBuf,
+    hash: String,
+    size: u64,
+    last_used: SystemTime,
+}
+
+fn read_thumbnail_cache() -> Result<Vec<CachedThumbnail>> {
+    let mut thumbnails = Vec::new();
+
+    for entry in std::fs::read_dir(get_dir_path(Dir::Thumbnails)?)? {
+        let entry = entry?;
+        let file_metadata = entry.metadata()?;
+        if !file_metadata.is_file() {
+            continue;
+        }
+
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        thumbnails.push(CachedThumbnail {
+            path: entry.path(),
+            hash: hash_from_thumbnail_name(&file_name).to_owned(),
+            size: file_metadata.len(),
+            last_used: file_metadata.accessed()?,
+        });
+    }
+
+    Ok(thumbnails)
+}
+
+#[tauri::command]
+pub async fn get_thumbnail_cache_size() -> Result<u64> {
+    let cache_size = read_thumbnail_cache()?
+        .iter()
+        .map(|thumbnail| thumbnail.size)
+        .sum();
+
+    Ok(cache_size)
+}
+
+/// Removes thumbnails of content that no longer exists, then evicts the least recently
+/// used thumbnails until the cache fits the limit from preferences. Returns the freed bytes.
+/// It goes over the whole library and thumbnail dir, so it only runs at startup or on request.
+#[tauri::command]
+pub async fn clean_thumbnail_cache() -> Result<u64> {
+    let all_metadata = metadata::get_all_metadata().await?;
+    let mut known_hashes: HashSet<&str> = all_metadata
+        .iter()
+        .map(|metadata| metadata.hash.as_str())
+        .collect();
+    // Link thumbnails are the screenshots sent by the extension, once evicted they're gone for good.
+    // The same goes for purged items, which only get their thumbnails back through undo.
+    let mut kept_hashes: HashSet<&str> = all_metadata
+        .iter()
+        .filter(|metadata| matches!(metadata.content_type, ContentType::Link))
+        .map(|metadata| metadata.hash.as_str())
+        .collect();
+    let logged_hashes = change_log::get_logged_hashes()?;
+    for hash in logged_hashes.iter() {
+        if known_hashes.insert(hash.as_str()) {
+            kept_hashes.insert(hash.as_str());
+        }
+    }
+
+    let mut freed_bytes = 0;
+    let mut thumbnails = Vec::new();
+    for thumbnail in read_thumbnail_cache()? {
+        if known_hashes.contains(thumbnail.hash.as_str()) {
+            thumbnails.push(thumbnail);
+        } else {
+            remove_if_exists(&thumbnail.path)?;
+            freed_bytes += thumbnail.size;
+        }
+    }
+
+    let cache_limit = u64::from(get_preferences()?.thumbnail_cache_limit_mb) * 1024 * 1024;
+    let mut cache_size: u64 = thumbnails.iter().map(|thumbnail| thumbnail.size).sum();
+
+    thumbnails.sort_by_key(|thumbnail| thumbnail.last_used);
+    for thumbnail in thumbnails {
+        if cache_size <= cache_limit {
+            break;
+        }
+        if kept_hashes.contains(thumbnail.hash.as_str()) {
+            continue;
+        }
+
+        remove_if_exists(&thumbnail.path)?;
+        cache_size -= thumbnail.size;
+        freed_bytes += thumbnail.size;
+    }
+
+    Ok(freed_bytes)
+}
@@ -0,0 +1,704 @@
+//! Reads dimensions and duration straight from the container headers of MP4/MOV,
+//! Matroska/WebM and AVI files, without decoding or even reading any of the media data.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoInfo {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// In seconds
+    pub duration: Option<f64>,
+}
+
+enum ContainerFormat {
+    IsoBmff,
+    Matroska,
+    Avi,
+}
+
+const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+
+// Matroska element IDs, including their length marker bits
+const MKV_SEGMENT: u32 = 0x1853_8067;
+const MKV_INFO: u32 = 0x1549_A966;
+const MKV_TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const MKV_DURATION: u32 = 0x4489;
+const MKV_TRACKS: u32 = 0x1654_AE6B;
+const MKV_TRACK_ENTRY: u32 = 0xAE;
+const MKV_TRACK_TYPE: u32 = 0x83;
+const MKV_VIDEO: u32 = 0xE0;
+const MKV_PIXEL_WIDTH: u32 = 0xB0;
+const MKV_PIXEL_HEIGHT: u32 = 0xBA;
+const MKV_CLUSTER: u32 = 0x1F43_B675;
+const MKV_TRACK_TYPE_VIDEO: u64 = 1;
+const MKV_DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+pub fn parse_video_info(video_path: &str) -> Option<VideoInfo> {
+    let file = File::open(video_path).ok()?;
+    parse_video_container(&mut BufReader::new(file)).ok()?
+}
+
+/// Returns `None` if the container format isn't one we know how to read
+pub fn parse_video_container<R: Read + Seek>(reader: &mut R) -> io::Result<Option<VideoInfo>> {
+    let mut magic = [0; 12];
+    let read = read_up_to(reader, &mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let info = match detect_format(&magic[..read]) {
+        Some(ContainerFormat::IsoBmff) => parse_iso_bmff(reader)?,
+        Some(ContainerFormat::Matroska) => parse_matroska(reader)?,
+        Some(ContainerFormat::Avi) => parse_avi(reader)?,
+        None => return Ok(None),
+    };
+
+    Ok(Some(info))
+}
+
+fn detect_format(magic: &[u8]) -> Option<ContainerFormat> {
+    if magic.len() >= 4 && magic[..4] == EBML_MAGIC {
+        return Some(ContainerFormat::Matroska);
+    }
+    if magic.len() >= 12 && &magic[..4] == b"RIFF" && &magic[8..12] == b"AVI " {
+        return Some(ContainerFormat::Avi);
+    }
+    // Older QuickTime files don't always start with an ftyp box
+    if magic.len() >= 8
+        && matches!(
+            &magic[4..8],
+            b"ftyp" | b"moov" | b"mdat" | b"free" | b"wide" | b"skip"
+        )
+    {
+        return Some(ContainerFormat::IsoBmff);
+    }
+
+    None
+}
+
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..])? {
+            0 => break,
+            read => total += read,
+        }
+    }
+    Ok(total)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32_be<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_u32_le<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64_be<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn stream_length<R: Seek>(reader: &mut R) -> io::Result<u64> {
+    let position = reader.stream_position()?;
+    let length = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    Ok(length)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+// MP4 / MOV
+
+struct IsoBox {
+    box_type: [u8; 4],
+    /// Position of the first byte after the box header
+    content_start: u64,
+    end: u64,
+}
+
+fn read_box_header<R: Read + Seek>(reader: &mut R, parent_end: u64) -> io::Result<Option<IsoBox>> {
+    let start = reader.stream_position()?;
+    if start + 8 > parent_end {
+        return Ok(None);
+    }
+
+    let size = read_u32_be(reader)?;
+    let mut box_type = [0; 4];
+    reader.read_exact(&mut box_type)?;
+
+    let end = match size {
+        0 => parent_end,
+        1 => start
+            .checked_add(read_u64_be(reader)?)
+            .ok_or_else(|| invalid_data("MP4 box size is too large"))?,
+        size => start + u64::from(size),
+    };
+    let content_start = reader.stream_position()?;
+    if end < content_start || end > parent_end {
+        return Err(invalid_data("MP4 box extends beyond its parent"));
+    }
+
+    Ok(Some(IsoBox {
+        box_type,
+        content_start,
+        end,
+    }))
+}
+
+/// Calls `visit` for every direct child box within `start..end`, the reader is positioned at
+/// the child's content and doesn't need to be restored by the callback
+fn for_each_box<R, F>(reader: &mut R, start: u64, end: u64, mut visit: F) -> io::Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&mut R, &IsoBox) -> io::Result<()>,
+{
+    reader.seek(SeekFrom::Start(start))?;
+    while let Some(iso_box) = read_box_header(reader, end)? {
+        visit(reader, &iso_box)?;
+        reader.seek(SeekFrom::Start(iso_box.end))?;
+    }
+    Ok(())
+}
+
+struct Mp4Track {
+    is_video: bool,
+    width: u32,
+    height: u32,
+    rotated: bool,
+}
+
+fn parse_iso_bmff<R: Read + Seek>(reader: &mut R) -> io::Result<VideoInfo> {
+    let file_end = stream_length(reader)?;
+    let mut info = VideoInfo::default();
+    let mut tracks: Vec<Mp4Track> = Vec::new();
+
+    for_each_box(reader, 0, file_end, |reader, top_level| {
+        if &top_level.box_type != b"moov" {
+            return Ok(());
+        }
+
+        for_each_box(
+            reader,
+            top_level.content_start,
+            top_level.end,
+            |reader, child| {
+                match &child.box_type {
+                    b"mvhd" => info.duration = parse_mvhd(reader)?,
+                    b"trak" => tracks.push(parse_trak(reader, child)?),
+                    _ => {}
+                }
+                Ok(())
+            },
+        )
+    })?;
+
+    // Audio tracks have no dimensions, so if the handler wasn't found we take any track with some
+    let video_track = tracks
+        .iter()
+        .find(|track| track.is_video && track.width > 0)
+        .or_else(|| tracks.iter().find(|track| track.width > 0));
+
+    if let Some(track) = video_track {
+        let (width, height) = if track.rotated {
+            (track.height, track.width)
+        } else {
+            (track.width, track.height)
+        };
+        info.width = Some(width);
+        info.height = Some(height);
+    }
+
+    Ok(info)
+}
+
+fn parse_mvhd<R: Read + Seek>(reader: &mut R) -> io::Result<Option<f64>> {
+    let version = read_u8(reader)?;
+    reader.seek(SeekFrom::Current(3))?;
+
+    let (timescale, duration) = if version == 1 {
+        reader.seek(SeekFrom::Current(16))?;
+        (read_u32_be(reader)?, read_u64_be(reader)?)
+    } else {
+        reader.seek(SeekFrom::Current(8))?;
+        (read_u32_be(reader)?, u64::from(read_u32_be(reader)?))
+    };
+
+    // An all ones duration means it's unknown
+    if timescale == 0 || duration == u64::from(u32::MAX) || duration == u64::MAX {
+        return Ok(None);
+    }
+
+    Ok(Some(duration as f64 / f64::from(timescale)))
+}
+
+fn parse_trak<R: Read + Seek>(reader: &mut R, trak: &IsoBox) -> io::Result<Mp4Track> {
+    let mut track = Mp4Track {
+        is_video: false,
+        width: 0,
+        height: 0,
+        rotated: false,
+    };
+
+    for_each_box(reader, trak.content_start, trak.end, |reader, child| {
+        match &child.box_type {
+            b"tkhd" => parse_tkhd(reader, &mut track)?,
+            b"mdia" => {
+                for_each_box(
+                    reader,
+                    child.content_start,
+                    child.end,
+                    |reader, mdia_child| {
+                        if &mdia_child.box_type == b"hdlr" {
+                            // version, flags and pre_defined come before the handler type
+                            reader.seek(SeekFrom::Current(8))?;
+                            let mut handler_type = [0; 4];
+                            reader.read_exact(&mut handler_type)?;
+                            track.is_video = &handler_type == b"vide";
+                        }
+                        Ok(())
+                    },
+                )?;
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    Ok(track)
+}
+
+fn parse_tkhd<R: Read + Seek>(reader: &mut R, track: &mut Mp4Track) -> io::Result<()> {
+    let version = read_u8(reader)?;
+    reader.seek(SeekFrom::Current(3))?;
+
+    // Timestamps, track id and duration, which are wider in version 1
+    let header_fields = if version == 1 { 32 } else { 20 };
+    // Followed by reserved, layer, alternate group, volume and another reserved
+    reader.seek(SeekFrom::Current(header_fields + 16))?;
+
+    let mut matrix = [0_i32; 9];
+    for value in matrix.iter_mut() {
+        *value = read_u32_be(reader)? as i32;
+    }
+
+    // Width and height are 16.16 fixed point numbers
+    track.width = read_u32_be(reader)? >> 16;
+    track.height = read_u32_be(reader)? >> 16;
+    // Phones record portrait video as landscape and rotate it through the matrix,
+    // a rotation by 90 or 270 degrees zeroes out the scaling components
+    track.rotated = matrix[0] == 0 && matrix[4] == 0 && matrix[1] != 0 && matrix[3] != 0;
+
+    Ok(())
+}
+
+// Matroska / WebM
+
+struct EbmlElement {
+    id: u32,
+    /// `None` for elements of unknown size, which run until their parent ends
+    size: Option<u64>,
+    content_start: u64,
+}
+
+/// Reads a variable length integer, returning the value and its length in bytes
+fn read_vint<R: Read>(reader: &mut R, keep_marker: bool) -> io::Result<(u64, usize)> {
+    let first = read_u8(reader)?;
+    let length = first.leading_zeros() as usize + 1;
+    if length > 8 {
+        return Err(invalid_data("Invalid EBML variable length integer"));
+    }
+
+    let mut value = if keep_marker {
+        u64::from(first)
+    } else {
+        u64::from(first) & (0xFF >> length)
+    };
+    for _ in 1..length {
+        value = (value << 8) | u64::from(read_u8(reader)?);
+    }
+
+    Ok((value, length))
+}
+
+fn read_ebml_element<R: Read + Seek>(reader: &mut R) -> io::Result<EbmlElement> {
+    let (id, id_length) = read_vint(reader, true)?;
+    if id_length > 4 {
+        return Err(invalid_data("EBML element ID is too long"));
+    }
+
+    let (size, size_length) = read_vint(reader, false)?;
+    let unknown_size = size == (1 << (7 * size_length)) - 1;
+
+    Ok(EbmlElement {
+        id: id as u32,
+        size: if unknown_size { None } else { Some(size) },
+        content_start: reader.stream_position()?,
+    })
+}
+
+fn read_ebml_uint<R: Read>(reader: &mut R, size: u64) -> io::Result<u64> {
+    if size > 8 {
+        return Err(invalid_data("EBML unsigned integer is too long"));
+    }
+
+    let mut value = 0;
+    for _ in 0..size {
+        value = (value << 8) | u64::from(read_u8(reader)?);
+    }
+    Ok(value)
+}
+
+fn read_ebml_float<R: Read>(reader: &mut R, size: u64) -> io::Result<f64> {
+    match size {
+        4 => {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf)?;
+            Ok(f64::from(f32::from_be_bytes(buf)))
+        }
+        8 => {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            Ok(f64::from_be_bytes(buf))
+        }
+        _ => Err(invalid_data("EBML float has an invalid size")),
+    }
+}
+
+/// Calls `visit` for every direct child element within `start..end`
+fn for_each_element<R, F>(reader: &mut R, start: u64, end: u64, mut visit: F) -> io::Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&mut R, &EbmlElement, u64) -> io::Result<bool>,
+{
+    let mut position = start;
+    while position < end {
+        reader.seek(SeekFrom::Start(position))?;
+        let element = match read_ebml_element(reader) {
+            Ok(element) => element,
+            // Files that got cut off still have perfectly usable headers
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error),
+        };
+        let element_end = match element.size {
+            Some(size) => (element.content_start + size).min(end),
+            None => end,
+        };
+
+        if !visit(reader, &element, element_end)? {
+            break;
+        }
+        position = element_end;
+    }
+    Ok(())
+}
+
+fn parse_matroska<R: Read + Seek>(reader: &mut R) -> io::Result<VideoInfo> {
+    let file_end = stream_length(reader)?;
+    let mut info = VideoInfo::default();
+    let mut timecode_scale = MKV_DEFAULT_TIMECODE_SCALE;
+    let mut raw_duration: Option<f64> = None;
+
+    for_each_element(reader, 0, file_end, |reader, segment, segment_end| {
+        if segment.id != MKV_SEGMENT {
+            return Ok(true);
+        }
+
+        let mut found_info = false;
+        let mut found_tracks = false;
+        for_each_element(
+            reader,
+            segment.content_start,
+            segment_end,
+            |reader, child, child_end| {
+                match child.id {
+                    MKV_INFO => {
+                        found_info = true;
+                        for_each_element(
+                            reader,
+                            child.content_start,
+                            child_end,
+                            |reader, field, _| {
+                                let size = field.size.unwrap_or(0);
+                                match field.id {
+                                    MKV_TIMECODE_SCALE => {
+                                        timecode_scale = read_ebml_uint(reader, size)?
+                                    }
+                                    MKV_DURATION => {
+                                        raw_duration = Some(read_ebml_float(reader, size)?)
+                                    }
+                                    _ => {}
+                                }
+                                Ok(true)
+                            },
+                        )?;
+                    }
+                    MKV_TRACKS => {
+                        found_tracks = true;
+                        parse_matroska_tracks(reader, child.content_start, child_end, &mut info)?;
+                    }
+                    // Media data follows the headers, there's nothing more for us past this point
+                    MKV_CLUSTER => return Ok(false),
+                    _ => {}
+                }
+                Ok(!(found_info && found_tracks))
+            },
+        )?;
+
+        Ok(false)
+    })?;
+
+    if let Some(raw_duration) = raw_duration {
+        info.duration = Some(raw_duration * timecode_scale as f64 / 1_000_000_000.0);
+    }
+
+    Ok(info)
+}
+
+fn parse_matroska_tracks<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    info: &mut VideoInfo,
+) -> io::Result<()> {
+    for_each_element(
+        reader,
+        start,
+        end,
+        |reader, track_entry, track_entry_end| {
+            if track_entry.id != MKV_TRACK_ENTRY {
+                return Ok(true);
+            }
+
+            let mut track_type = 0;
+            let mut width = None;
+            let mut height = None;
+            for_each_element(
+                reader,
+                track_entry.content_start,
+                track_entry_end,
+                |reader, field, field_end| {
+                    match field.id {
+                        MKV_TRACK_TYPE => {
+                            track_type = read_ebml_uint(reader, field.size.unwrap_or(0))?
+                        }
+                        MKV_VIDEO => {
+                            for_each_element(
+                                reader,
+                                field.content_start,
+                                field_end,
+                                |reader, video, _| {
+                                    let size = video.size.unwrap_or(0);
+                                    match video.id {
+                                        MKV_PIXEL_WIDTH => {
+                                            width = Some(read_ebml_uint(reader, size)? as u32)
+                                        }
+                                        MKV_PIXEL_HEIGHT => {
+                                            height = Some(read_ebml_uint(reader, size)? as u32)
+                                        }
+                                        _ => {}
+                                    }
+                                    Ok(true)
+                                },
+                            )?;
+                        }
+                        _ => {}
+                    }
+                    Ok(true)
+                },
+            )?;
+
+            if track_type == MKV_TRACK_TYPE_VIDEO && width.is_some() && height.is_some() {
+                info.width = width;
+                info.height = height;
+                return Ok(false);
+            }
+            Ok(true)
+        },
+    )
+}
+
+// AVI
+
+fn parse_avi<R: Read + Seek>(reader: &mut R) -> io::Result<VideoInfo> {
+    let file_end = stream_length(reader)?;
+    let mut info = VideoInfo::default();
+
+    // Skip the RIFF header, the main AVI header sits inside the first LIST chunk
+    let mut position = 12;
+    while position + 8 <= file_end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut chunk_id = [0; 4];
+        reader.read_exact(&mut chunk_id)?;
+        let chunk_size = u64::from(read_u32_le(reader)?);
+
+        if &chunk_id == b"LIST" {
+            let mut list_type = [0; 4];
+            reader.read_exact(&mut list_type)?;
+            if &list_type == b"hdrl" {
+                parse_avi_header_list(reader, position + 12, position + 8 + chunk_size, &mut info)?;
+                break;
+            }
+        }
+
+        position = next_riff_chunk(position, chunk_size)?;
+    }
+
+    Ok(info)
+}
+
+fn parse_avi_header_list<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    info: &mut VideoInfo,
+) -> io::Result<()> {
+    let mut position = start;
+    while position + 8 <= end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut chunk_id = [0; 4];
+        reader.read_exact(&mut chunk_id)?;
+        let chunk_size = u64::from(read_u32_le(reader)?);
+
+        if &chunk_id == b"avih" {
+            let micro_seconds_per_frame = read_u32_le(reader)?;
+            // Max bytes per second, padding granularity and flags
+            reader.seek(SeekFrom::Current(12))?;
+            let total_frames = read_u32_le(reader)?;
+            // Initial frames, streams and suggested buffer size
+            reader.seek(SeekFrom::Current(12))?;
+            let width = read_u32_le(reader)?;
+            let height = read_u32_le(reader)?;
+
+            if width > 0 && height > 0 {
+                info.width = Some(width);
+                info.height = Some(height);
+            }
+            if micro_seconds_per_frame > 0 && total_frames > 0 {
+                info.duration = Some(
+                    f64::from(total_frames) * f64::from(micro_seconds_per_frame) / 1_000_000.0,
+                );
+            }
+            return Ok(());
+        }
+
+        position = next_riff_chunk(position, chunk_size)?;
+    }
+
+    Ok(())
+}
+
+/// Chunks are padded to an even size
+fn next_riff_chunk(position: u64, chunk_size: u64) -> io::Result<u64> {
+    position
+        .checked_add(8 + chunk_size + (chunk_size & 1))
+        .ok_or_else(|| invalid_data("AVI chunk size is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn parse_fixture(name: &str) -> VideoInfo {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name);
+        let mut file = BufReader::new(File::open(path).unwrap());
+        parse_video_container(&mut file).unwrap().unwrap()
+    }
+
+    fn assert_duration(info: &VideoInfo, expected: f64) {
+        let duration = info.duration.expect("duration should be known");
+        assert!(
+            (duration - expected).abs() < 0.01,
+            "expected {} seconds but got {}",
+            expected,
+            duration
+        );
+    }
+
+    #[test]
+    fn parses_mp4() {
+        let info = parse_fixture("video.mp4");
+        assert_eq!(info.width, Some(320));
+        assert_eq!(info.height, Some(240));
+        assert_duration(&info, 2.5);
+    }
+
+    #[test]
+    fn parses_rotated_mp4_as_portrait() {
+        let info = parse_fixture("rotated.mp4");
+        assert_eq!(info.width, Some(1080));
+        assert_eq!(info.height, Some(1920));
+        assert_duration(&info, 4.0);
+    }
+
+    #[test]
+    fn parses_mov_without_ftyp() {
+        let info = parse_fixture("video.mov");
+        assert_eq!(info.width, Some(640));
+        assert_eq!(info.height, Some(480));
+        assert_duration(&info, 10.0);
+    }
+
+    #[test]
+    fn parses_mkv() {
+        let info = parse_fixture("video.mkv");
+        assert_eq!(info.width, Some(1280));
+        assert_eq!(info.height, Some(720));
+        assert_duration(&info, 3.2);
+    }
+
+    #[test]
+    fn parses_webm_with_unknown_segment_size() {
+        let info = parse_fixture("video.webm");
+        assert_eq!(info.width, Some(854));
+        assert_eq!(info.height, Some(480));
+        assert_duration(&info, 1.5);
+    }
+
+    #[test]
+    fn parses_avi() {
+        let info = parse_fixture("video.avi");
+        assert_eq!(info.width, Some(720));
+        assert_eq!(info.height, Some(576));
+        assert_duration(&info, 5.0);
+    }
+
+    #[test]
+    fn rejects_box_size_past_the_end_of_the_address_space() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&8u32.to_be_bytes());
+        bytes.extend_from_slice(b"free");
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(b"moov");
+        bytes.extend_from_slice(&u64::MAX.to_be_bytes());
+
+        let mut reader = io::Cursor::new(bytes);
+        assert!(parse_video_container(&mut reader).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_containers() {
+        let mut reader = io::Cursor::new(b"definitely not a video file".to_vec());
+        assert_eq!(parse_video_container(&mut reader).unwrap(), None);
+    }
+}
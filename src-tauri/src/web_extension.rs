@@ -1,5 +1,7 @@
 use crate::{
-    content_manager::add_url_from_extension,
+    content_manager::{
+        add_image_from_extension, add_selection_from_extension, add_url_from_extension,
+    },
     foc_error::{FocError, Result},
 };
 #[cfg(target_os = "windows")]
@@ -13,17 +15,37 @@ use tauri::{AppHandle, Manager};
 
 static ID: OnceCell<String> = OnceCell::new();
 
+/// A message from the extension. Everything besides the command, name and url is optional,
+/// so older versions of the extension that only send a screenshot keep working.
 #[derive(Deserialize, Debug)]
-pub struct Request<'a> {
-    pub name: &'a str,
-    pub url: &'a str,
-    pub image: &'a str,
-    pub command: &'a str,
+pub struct Request {
+    pub command: String,
+    pub name: String,
+    pub url: String,
+    /// Screenshot of the page as a base64 data URL, or the image itself for `saveImage`
+    pub image: Option<String>,
+    /// Address of the image for `saveImage`, it's downloaded when `image` isn't sent
+    pub source_url: Option<String>,
+    /// Readable text of the page without navigation, ads and the like
+    pub text: Option<String>,
+    /// Whatever the user had selected on the page
+    pub selection: Option<String>,
+    /// The page's meta description
+    pub description: Option<String>,
+    /// The page's icon as a base64 data URL
+    pub favicon: Option<String>,
+    /// Tags picked in the extension popup
+    pub tags: Option<Vec<String>>,
+    /// Notes written in the extension popup
+    pub notes: Option<String>,
 }
 
 #[derive(Debug)]
 enum Command {
-    SaveUrl,
+    Url,
+    Page,
+    Image,
+    Selection,
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -43,13 +65,16 @@ struct NativeMessagingManifest {
 }
 
 impl Command {
-    pub fn from_string(command: &str) -> Self {
+    pub fn from_string(command: &str) -> Result<Self> {
         match command {
-            "saveUrl" => Command::SaveUrl,
-            _ => panic!(
+            "saveUrl" => Ok(Command::Url),
+            "savePage" => Ok(Command::Page),
+            "saveImage" => Ok(Command::Image),
+            "saveSelection" => Ok(Command::Selection),
+            _ => Err(FocError::Extension(format!(
                 "Unknown command recieved from focular web extension: {}",
                 command
-            ),
+            ))),
         }
     }
 }
@@ -141,8 +166,10 @@ pub fn start_extension_listener(app_handle: AppHandle) {
                 continue;
             }
 
-            let request: Request = serde_json::from_str(&buffer).unwrap();
-            handle_request(&request, &app_handle);
+            match serde_json::from_str::<Request>(&buffer) {
+                Ok(request) => handle_request(&request, &app_handle),
+                Err(error) => println!("Couldn't parse extension message: {}", error),
+            }
             buffer.pop();
         }
     });
@@ -229,8 +256,10 @@ pub fn start_extension_listener(app_handle: AppHandle) {
                 continue;
             };
 
-            let request: Request = serde_json::from_str(&buffer).unwrap();
-            handle_request(&request, &app_handle);
+            match serde_json::from_str::<Request>(&buffer) {
+                Ok(request) => handle_request(&request, &app_handle),
+                Err(error) => println!("Couldn't parse extension message: {}", error),
+            }
             buffer.pop();
         }
     });
@@ -274,21 +303,36 @@ fn send_response() -> Result<()> {
 }
 
 fn handle_request(request: &Request, app_handle: &AppHandle) {
-    match Command::from_string(request.command) {
-        Command::SaveUrl => {
-            tauri::async_runtime::block_on(add_url_from_extension(request)).unwrap();
-            app_handle
-                .emit_to(
-                    "main",
-                    "web_extension_event",
-                    Payload {
-                        command: "saveUrl".to_owned(),
-                        message: "".to_owned(),
-                    },
-                )
-                .unwrap();
+    let result = Command::from_string(&request.command).and_then(|command| match command {
+        // Saving a page is saving its url, only with tags and notes from the popup
+        Command::Url | Command::Page => {
+            tauri::async_runtime::block_on(add_url_from_extension(request))
         }
-    }
+        Command::Image => tauri::async_runtime::block_on(add_image_from_extension(request)),
+        Command::Selection => tauri::async_runtime::block_on(add_selection_from_extension(request)),
+    });
+
+    // The frontend gets told either way, an empty message means it worked
+    let message = match result {
+        Ok(_) => "".to_owned(),
+        Err(error) => {
+            println!(
+                "Couldn't handle {} from the extension: {}",
+                request.command, error
+            );
+            error.to_string()
+        }
+    };
+    app_handle
+        .emit_to(
+            "main",
+            "web_extension_event",
+            Payload {
+                command: request.command.clone(),
+                message,
+            },
+        )
+        .unwrap();
 }
 
 fn to_u32(arr: [u8; 4]) -> u32 {
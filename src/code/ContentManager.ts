@@ -1,9 +1,24 @@
 import type { FocError } from "./../types/FocError";
 import type { Preferences } from "./../../src-tauri/bindings/Preferences";
+import type { ThumbnailSize } from "./../../src-tauri/bindings/ThumbnailSize";
+import type { CustomField } from "./../../src-tauri/bindings/CustomField";
+import type { CustomFieldValue } from "./../../src-tauri/bindings/CustomFieldValue";
+import type { FieldType } from "./../../src-tauri/bindings/FieldType";
+import type { SearchFilter } from "./../../src-tauri/bindings/SearchFilter";
+import type { BulkUpdate } from "./../../src-tauri/bindings/BulkUpdate";
+import type { MetadataPatch } from "./../../src-tauri/bindings/MetadataPatch";
+import type { ChangeLogEntry } from "./../../src-tauri/bindings/ChangeLogEntry";
+import type { Collection } from "./../../src-tauri/bindings/Collection";
+import type { SavedSearch } from "./../../src-tauri/bindings/SavedSearch";
+import type { RuleCondition } from "./../../src-tauri/bindings/RuleCondition";
+import type { TagRule } from "./../../src-tauri/bindings/TagRule";
+import type { TagRuleMatch } from "./../../src-tauri/bindings/TagRuleMatch";
+import type { ItemLink } from "./../../src-tauri/bindings/ItemLink";
+import type { LinkedItem } from "./../../src-tauri/bindings/LinkedItem";
+import type { Note } from "./../../src-tauri/bindings/Note";
 import { AlertColor, allMetadata, allTags } from "./valuesStore";
 import { convertFileSrc, invoke } from "@tauri-apps/api/tauri";
 import type { Metadata } from "../types/Metadata";
-import { cacheDir } from "@tauri-apps/api/path";
 import { preferences } from "./settingsStore";
 import { alerts } from "./valuesStore";
 import { get } from "svelte/store";
@@ -37,10 +52,13 @@ async function updatePreferences(): Promise<void> {
   );
 }
 
-async function searchContent(query: string): Promise<Metadata[]> {
+async function searchContent(
+  query: string,
+  filter?: SearchFilter
+): Promise<Metadata[]> {
   let searchResults: Metadata[];
 
-  await invoke("search_content", { query: query })
+  await invoke("search_content", { query: query, filter: filter ?? null })
     .then((result: Metadata[]) => {
       searchResults = result;
     })
@@ -67,30 +85,79 @@ async function getMetadataByHashes(hashes: Array<string>): Promise<Metadata[]> {
   return foundMetadata;
 }
 
-async function deleteMetadata(metadata: Array<Metadata>): Promise<void> {
+async function deleteMetadata(
+  metadata: Array<Metadata>,
+  deleteFiles: boolean = false
+): Promise<void> {
   let hashes = metadata.map((metadata) => metadata.hash);
 
-  await invoke("delete_content", { hashes: hashes }).catch(
+  await invoke("delete_content", {
+    hashes: hashes,
+    deleteFiles: deleteFiles,
+  }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function restoreContent(hashes: Array<string>): Promise<Metadata[]> {
+  let restored: Metadata[] = [];
+
+  await invoke("restore_content", { hashes: hashes })
+    .then((result: Metadata[]) => {
+      restored = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return restored;
+}
+
+async function purgeContent(hashes: Array<string>): Promise<void> {
+  await invoke("purge_content", { hashes: hashes }).catch(
     (error: FocError) => {
       showErrorAlert(error);
     }
   );
 }
 
+async function getTrashedContent(): Promise<Metadata[]> {
+  let trashed: Metadata[] = [];
+
+  await invoke("get_trashed_content", {})
+    .then((result: Metadata[]) => {
+      trashed = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return trashed;
+}
+
+async function emptyTrash(): Promise<void> {
+  await invoke("empty_trash", {}).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
 async function openInExplorer(path: string): Promise<void> {
   await invoke("open_in_explorer", { path: path }).catch((error: FocError) => {
     showErrorAlert(error);
   });
 }
 
-async function getThumbnailPath(hash: string): Promise<string> {
+async function getThumbnailPath(
+  hash: string,
+  size: ThumbnailSize = "Large"
+): Promise<string> {
   let thumbnailPath: string;
 
-  await cacheDir()
-    .then((dir) => {
-      let thumbnailName = hash.toString().replace(/,/g, "");
-      const file = dir + "Focular/thumbnails/" + thumbnailName + ".png";
-      thumbnailPath = convertFileSrc(file);
+  await invoke("get_thumbnail_path", { hash: hash, size: size })
+    .then((result: string | null) => {
+      thumbnailPath = result === null ? null : convertFileSrc(result);
     })
     .catch((error: FocError) => {
       showErrorAlert(error);
@@ -100,6 +167,51 @@ async function getThumbnailPath(hash: string): Promise<string> {
   return thumbnailPath;
 }
 
+async function getPreviewPath(hash: string): Promise<string> {
+  let previewPath: string;
+
+  await invoke("get_preview_path", { hash: hash })
+    .then((result: string | null) => {
+      previewPath = result === null ? null : convertFileSrc(result);
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return previewPath;
+}
+
+async function getFaviconPath(hash: string): Promise<string> {
+  let faviconPath: string;
+
+  await invoke("get_favicon_path", { hash: hash })
+    .then((result: string | null) => {
+      faviconPath = result === null ? null : convertFileSrc(result);
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return faviconPath;
+}
+
+async function regenerateThumbnails(hashes?: Array<string>): Promise<number> {
+  let regenerated: number;
+
+  await invoke("regenerate_thumbnails", { hashes: hashes ?? null })
+    .then((result: number) => {
+      regenerated = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return regenerated;
+}
+
 async function getFileSize(filePath: string): Promise<number> {
   let fileSize: number;
 
@@ -116,18 +228,134 @@ async function getFileSize(filePath: string): Promise<number> {
 }
 
 async function updateMetadata(metadata: Metadata): Promise<void> {
-  metadata.timestamp_modified = Math.trunc(Date.now() / 1000);
+  await patchMetadata(
+    metadata.hash,
+    { name: metadata.name, tags: metadata.tags, notes: metadata.notes },
+    metadata.timestamp_modified
+  );
+}
+
+async function patchMetadata(
+  hash: string,
+  patch: MetadataPatch,
+  expectedTimestampModified?: number
+): Promise<void> {
+  await invoke("patch_content", {
+    hash: hash,
+    patch: patch,
+    expectedTimestampModified: expectedTimestampModified ?? null,
+  })
+    .then((patched: Metadata) => {
+      replaceMetadata([patched]);
+    })
+    .catch(async (error: FocError) => {
+      showErrorAlert(error);
+      if (error.code === 18) {
+        // Someone else changed the item in the meantime, show their version
+        replaceMetadata(await getMetadataByHashes([hash]));
+      }
+    });
+}
 
+function replaceMetadata(updated: Array<Metadata>): void {
   allMetadata.update((current) => {
-    current[current.findIndex((m) => m.hash === metadata.hash)] = metadata;
+    for (let metadata of updated) {
+      let index = current.findIndex((m) => m.hash === metadata.hash);
+      if (index !== -1) {
+        // Components hold on to these objects, so they have to see the new timestamp too
+        Object.assign(current[index], metadata);
+      }
+    }
     return current;
   });
+}
 
-  await invoke("update_content", { metadata: metadata }).catch(
-    (error: FocError) => {
+async function bulkUpdateContent(
+  hashes: Array<string>,
+  update: BulkUpdate
+): Promise<void> {
+  await invoke("bulk_update_content", { hashes: hashes, update: update })
+    .then((updated: Metadata[]) => {
+      replaceMetadata(updated);
+    })
+    .catch((error: FocError) => {
       showErrorAlert(error);
-    }
-  );
+    });
+}
+
+async function setRating(
+  metadata: Array<Metadata>,
+  rating: number
+): Promise<void> {
+  let hashes = metadata.map((metadata) => metadata.hash);
+
+  await invoke("set_rating", { hashes: hashes, rating: rating })
+    .then(() => {
+      metadata.forEach((metadata) => (metadata.rating = rating));
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+    });
+}
+
+async function setFavorite(
+  metadata: Array<Metadata>,
+  favorite: boolean
+): Promise<void> {
+  let hashes = metadata.map((metadata) => metadata.hash);
+
+  await invoke("set_favorite", { hashes: hashes, favorite: favorite })
+    .then(() => {
+      metadata.forEach((metadata) => (metadata.favorite = favorite));
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+    });
+}
+
+async function getItemHistory(hash: string): Promise<ChangeLogEntry[]> {
+  let history: ChangeLogEntry[];
+
+  await invoke("get_item_history", { hash: hash })
+    .then((result: ChangeLogEntry[]) => {
+      history = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return history;
+}
+
+async function undoChanges(count?: number): Promise<string[]> {
+  let changedHashes: string[] = [];
+
+  await invoke("undo_changes", { count: count ?? null })
+    .then((result: string[]) => {
+      changedHashes = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return changedHashes;
+}
+
+async function redoChanges(count?: number): Promise<string[]> {
+  let changedHashes: string[] = [];
+
+  await invoke("redo_changes", { count: count ?? null })
+    .then((result: string[]) => {
+      changedHashes = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return changedHashes;
 }
 
 async function getAllTags(): Promise<void> {
@@ -141,6 +369,536 @@ async function getAllTags(): Promise<void> {
     });
 }
 
+async function getCustomFields(): Promise<CustomField[]> {
+  let customFields: CustomField[];
+
+  await invoke("get_custom_fields", {})
+    .then((result: CustomField[]) => {
+      customFields = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return customFields;
+}
+
+async function createCustomField(
+  name: string,
+  fieldType: FieldType,
+  options?: Array<string>
+): Promise<CustomField> {
+  let customField: CustomField;
+
+  await invoke("create_custom_field", {
+    name: name,
+    fieldType: fieldType,
+    options: options ?? null,
+  })
+    .then((result: CustomField) => {
+      customField = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return customField;
+}
+
+async function updateCustomField(field: CustomField): Promise<void> {
+  await invoke("update_custom_field", { field: field }).catch(
+    (error: FocError) => {
+      showErrorAlert(error);
+    }
+  );
+}
+
+async function deleteCustomField(id: number): Promise<void> {
+  await invoke("delete_custom_field", { id: id }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function getCustomFieldValues(hash: string): Promise<CustomFieldValue[]> {
+  let values: CustomFieldValue[];
+
+  await invoke("get_custom_field_values", { hash: hash })
+    .then((result: CustomFieldValue[]) => {
+      values = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return values;
+}
+
+async function setCustomFieldValue(
+  hash: string,
+  fieldId: number,
+  value: string | null
+): Promise<void> {
+  await invoke("set_custom_field_value", {
+    hash: hash,
+    fieldId: fieldId,
+    value: value,
+  }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function getCollections(): Promise<Collection[]> {
+  let collections: Collection[] = [];
+
+  await invoke("get_collections")
+    .then((result: Collection[]) => {
+      collections = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return collections;
+}
+
+async function createCollection(
+  name: string,
+  description?: string
+): Promise<Collection> {
+  let collection: Collection;
+
+  await invoke("create_collection", {
+    name: name,
+    description: description ?? null,
+  })
+    .then((result: Collection) => {
+      collection = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return collection;
+}
+
+async function updateCollection(collection: Collection): Promise<void> {
+  await invoke("update_collection", { collection: collection }).catch(
+    (error: FocError) => {
+      showErrorAlert(error);
+    }
+  );
+}
+
+async function deleteCollection(id: number): Promise<void> {
+  await invoke("delete_collection", { id: id }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function getCollectionItems(id: number): Promise<Metadata[]> {
+  let items: Metadata[] = [];
+
+  await invoke("get_collection_items", { id: id })
+    .then((result: Metadata[]) => {
+      items = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return items;
+}
+
+async function addToCollection(
+  collectionId: number,
+  hashes: string[]
+): Promise<void> {
+  await invoke("add_to_collection", {
+    collectionId: collectionId,
+    hashes: hashes,
+  }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function removeFromCollection(
+  collectionId: number,
+  hashes: string[]
+): Promise<void> {
+  await invoke("remove_from_collection", {
+    collectionId: collectionId,
+    hashes: hashes,
+  }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function reorderCollection(
+  collectionId: number,
+  hashes: string[]
+): Promise<void> {
+  await invoke("reorder_collection", {
+    collectionId: collectionId,
+    hashes: hashes,
+  }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function getSavedSearches(): Promise<SavedSearch[]> {
+  let savedSearches: SavedSearch[] = [];
+
+  await invoke("get_saved_searches")
+    .then((result: SavedSearch[]) => {
+      savedSearches = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return savedSearches;
+}
+
+async function createSavedSearch(
+  name: string,
+  query: string,
+  filter?: SearchFilter
+): Promise<SavedSearch> {
+  let savedSearch: SavedSearch;
+
+  await invoke("create_saved_search", {
+    name: name,
+    query: query,
+    filter: filter ?? null,
+  })
+    .then((result: SavedSearch) => {
+      savedSearch = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return savedSearch;
+}
+
+async function updateSavedSearch(
+  savedSearch: SavedSearch
+): Promise<SavedSearch> {
+  let updatedSavedSearch: SavedSearch;
+
+  await invoke("update_saved_search", { savedSearch: savedSearch })
+    .then((result: SavedSearch) => {
+      updatedSavedSearch = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return updatedSavedSearch;
+}
+
+async function deleteSavedSearch(id: number): Promise<void> {
+  await invoke("delete_saved_search", { id: id }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function runSavedSearch(id: number): Promise<Metadata[]> {
+  let searchResults: Metadata[] = [];
+
+  await invoke("run_saved_search", { id: id })
+    .then((result: Metadata[]) => {
+      searchResults = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return searchResults;
+}
+
+async function refreshSavedSearchCounts(): Promise<SavedSearch[]> {
+  let savedSearches: SavedSearch[] = [];
+
+  await invoke("refresh_saved_search_counts")
+    .then((result: SavedSearch[]) => {
+      savedSearches = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return savedSearches;
+}
+
+async function getTagRules(): Promise<TagRule[]> {
+  let tagRules: TagRule[] = [];
+
+  await invoke("get_tag_rules")
+    .then((result: TagRule[]) => {
+      tagRules = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return tagRules;
+}
+
+async function createTagRule(
+  name: string,
+  condition: RuleCondition,
+  pattern: string,
+  tags: string[]
+): Promise<TagRule> {
+  let tagRule: TagRule;
+
+  await invoke("create_tag_rule", {
+    name: name,
+    condition: condition,
+    pattern: pattern,
+    tags: tags,
+  })
+    .then((result: TagRule) => {
+      tagRule = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return tagRule;
+}
+
+async function updateTagRule(rule: TagRule): Promise<void> {
+  await invoke("update_tag_rule", { rule: rule }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function deleteTagRule(id: number): Promise<void> {
+  await invoke("delete_tag_rule", { id: id }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function previewTagRules(rules?: TagRule[]): Promise<TagRuleMatch[]> {
+  let matches: TagRuleMatch[] = [];
+
+  await invoke("preview_tag_rules", { rules: rules ?? null })
+    .then((result: TagRuleMatch[]) => {
+      matches = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return matches;
+}
+
+async function runTagRules(): Promise<void> {
+  await invoke("run_tag_rules")
+    .then((result: Metadata[]) => {
+      replaceMetadata(result);
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+    });
+}
+
+async function linkItems(
+  sourceHash: string,
+  targetHashes: string[],
+  relation: string
+): Promise<ItemLink[]> {
+  let links: ItemLink[] = [];
+
+  await invoke("link_items", {
+    sourceHash: sourceHash,
+    targetHashes: targetHashes,
+    relation: relation,
+  })
+    .then((result: ItemLink[]) => {
+      links = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return links;
+}
+
+async function unlinkItems(linkIds: number[]): Promise<void> {
+  await invoke("unlink_items", { linkIds: linkIds }).catch(
+    (error: FocError) => {
+      showErrorAlert(error);
+    }
+  );
+}
+
+async function getItemLinks(hash: string): Promise<LinkedItem[]> {
+  let linkedItems: LinkedItem[] = [];
+
+  await invoke("get_item_links", { hash: hash })
+    .then((result: LinkedItem[]) => {
+      linkedItems = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return linkedItems;
+}
+
+async function getNotes(hash: string): Promise<Note[]> {
+  let notes: Note[] = [];
+
+  await invoke("get_notes", { hash: hash })
+    .then((result: Note[]) => {
+      notes = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return notes;
+}
+
+async function addNote(hash: string, body: string): Promise<Note> {
+  let note: Note;
+
+  await invoke("add_note", { hash: hash, body: body })
+    .then((result: Note) => {
+      note = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return note;
+}
+
+async function updateNote(id: number, body: string): Promise<Note> {
+  let note: Note;
+
+  await invoke("update_note", { id: id, body: body })
+    .then((result: Note) => {
+      note = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return note;
+}
+
+async function deleteNote(id: number): Promise<void> {
+  await invoke("delete_note", { id: id }).catch((error: FocError) => {
+    showErrorAlert(error);
+  });
+}
+
+async function createNoteItem(name: string, body: string): Promise<Metadata> {
+  let metadata: Metadata;
+
+  await invoke("create_note_item", { name: name, body: body })
+    .then((result: Metadata) => {
+      metadata = result;
+      allMetadata.update((current) => [...current, result]);
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return metadata;
+}
+
+async function createSnippet(
+  name: string,
+  text: string,
+  language?: string
+): Promise<Metadata> {
+  let metadata: Metadata;
+
+  await invoke("create_snippet", {
+    name: name,
+    text: text,
+    language: language ?? null,
+  })
+    .then((result: Metadata) => {
+      metadata = result;
+      allMetadata.update((current) => [...current, result]);
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return metadata;
+}
+
+async function getSnippetText(hash: string): Promise<string> {
+  let text: string;
+
+  await invoke("get_snippet_text", { hash: hash })
+    .then((result: string) => {
+      text = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return text;
+}
+
+async function updateSnippetText(hash: string, text: string): Promise<void> {
+  await invoke("update_snippet_text", { hash: hash, text: text })
+    .then((result: Metadata) => {
+      replaceMetadata([result]);
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+    });
+}
+
+async function exportContent(
+  hashes: string[],
+  directory: string
+): Promise<string[]> {
+  let exportedPaths: string[] = [];
+
+  await invoke("export_content", { hashes: hashes, directory: directory })
+    .then((result: string[]) => {
+      exportedPaths = result;
+    })
+    .catch((error: FocError) => {
+      showErrorAlert(error);
+      return error;
+    });
+
+  return exportedPaths;
+}
+
 function showAlert(
   message: string,
   color: AlertColor,
@@ -179,14 +937,66 @@ export {
   addFiles,
   searchContent,
   deleteMetadata as deleteContent,
+  restoreContent,
+  purgeContent,
+  getTrashedContent,
+  emptyTrash,
   openInExplorer,
   getMetadataByHashes,
   getThumbnailPath,
+  getPreviewPath,
+  getFaviconPath,
+  regenerateThumbnails,
   getFileSize,
   getAllTags,
   updateMetadata,
+  patchMetadata,
+  bulkUpdateContent,
+  setRating,
+  getItemHistory,
+  undoChanges,
+  redoChanges,
+  setFavorite,
   getPreferences,
   updatePreferences,
+  getCustomFields,
+  createCustomField,
+  updateCustomField,
+  deleteCustomField,
+  getCustomFieldValues,
+  setCustomFieldValue,
+  getCollections,
+  createCollection,
+  updateCollection,
+  deleteCollection,
+  getCollectionItems,
+  addToCollection,
+  removeFromCollection,
+  reorderCollection,
+  getSavedSearches,
+  createSavedSearch,
+  updateSavedSearch,
+  deleteSavedSearch,
+  runSavedSearch,
+  refreshSavedSearchCounts,
+  getTagRules,
+  createTagRule,
+  updateTagRule,
+  deleteTagRule,
+  previewTagRules,
+  runTagRules,
+  linkItems,
+  unlinkItems,
+  getItemLinks,
+  getNotes,
+  addNote,
+  updateNote,
+  deleteNote,
+  createNoteItem,
+  createSnippet,
+  getSnippetText,
+  updateSnippetText,
+  exportContent,
   showAlert,
   showErrorAlert,
 };
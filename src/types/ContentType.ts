@@ -4,5 +4,13 @@ export enum ContentType {
   Video,
   Gif,
   Link,
+  Document,
+  Archive,
+  Font,
+  Text,
+  Code,
+  Model3D,
+  Note,
+  Snippet,
   Other,
 }
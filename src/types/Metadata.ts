@@ -13,6 +13,9 @@ export interface Metadata {
   width: number;
   height: number;
   duration: string;
+  rating: number;
+  favorite: boolean;
+  timestamp_trashed: number | null;
   timestamp_created: number;
   timestamp_modified: number;
 }
@@ -2,4 +2,5 @@ export enum Status {
   Valid,
   Deleted,
   Duplicate,
+  Trashed,
 }